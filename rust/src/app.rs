@@ -1,20 +1,47 @@
-use egui::epaint::QuadraticBezierShape;
-use egui::{epaint::CubicBezierShape, Key, Label, Pos2, Rect, Sense, Vec2};
+use egui::{epaint::CubicBezierShape, Label, Pos2, Rect, Sense, Shape, UiBuilder, Vec2};
 use indexmap::IndexMap;
-use petgraph::algo::toposort;
+use petgraph::algo::tarjan_scc;
 use petgraph::graph::NodeIndex;
 use petgraph::{Directed, IntoWeightedEdge};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 
 use petgraph::{algo::is_cyclic_directed, stable_graph::StableGraph};
 
-use crate::function_widget::{FunctionWidget, LinkVertex, WidgetMode};
+use crate::comments::Annotations;
+use crate::debugger::{self, Debugger};
+use crate::diagnostics::{Diagnostics, CYCLE_COLOR};
+use crate::diagram::{self, Placement};
+use crate::events::{self, EventTracker, GraphEvent, GraphState};
+use crate::executor::{BackgroundExecutor, EditPolicy, ExecutionMessage, RunScope, RunState};
+use crate::files;
+use crate::flow;
+use crate::function_widget::{FunctionWidget, WidgetMode, DEFAULT_PORT_HIT_SIZE};
+use crate::graph::{self, Graph, GraphLink, Layout, Reroute};
+use crate::grid::Grid;
+use crate::importer::ScriptImporter;
+use crate::inspector::ValueInspector;
+use crate::keymap::{Action, Keymap};
+use crate::layout::{self, layered_layout, Alignment};
+use crate::library;
+use crate::palette::{Command, CommandPalette, PaletteEntry, PaletteItem, PaletteMode};
+use crate::routing::{self, LinkStyle};
+use crate::runnable::{is_same_value, LinkVertex};
+use crate::script::{self, Imported};
+use crate::testing::TestPanel;
+use crate::trace::{self, ExecutionTrace, TraceEntry};
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Link {
     start: LinkVertex,
     end: LinkVertex,
     should_be_deleted: bool,
+    /// Points the link is routed through, in order from start to end.
+    #[serde(default)]
+    reroutes: Vec<Pos2>,
+    /// When a new value last went through the link, for the flow pulse.
+    #[serde(skip)]
+    propagated_at: Option<f64>,
 }
 
 impl From<LinkVertex> for NodeIndex<u32> {
@@ -35,6 +62,11 @@ impl IntoWeightedEdge<()> for &Link {
     }
 }
 
+const PORT_HIT_RADIUS: f32 = 8.0;
+const NEW_NODE_GAP: f32 = 60.0;
+const LINK_HIT_DISTANCE: f32 = 6.0;
+const KNOT_RADIUS: f32 = 4.0;
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(Deserialize, Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -43,6 +75,52 @@ pub struct TemplateApp {
     links: Vec<Link>,
     is_cyclic: bool,
     last_rect_id: usize,
+    step_count: u32,
+    grid: Grid,
+    link_style: LinkStyle,
+    show_link_values: bool,
+    port_hit_size: f32,
+    keymap: Keymap,
+    annotations: Annotations,
+    edit_policy: EditPolicy,
+    tests: TestPanel,
+    #[serde(skip)]
+    debugger: Debugger,
+    #[serde(skip)]
+    trace: ExecutionTrace,
+    #[serde(skip)]
+    inspector: ValueInspector,
+    #[serde(skip)]
+    is_dragging_link: bool,
+    #[serde(skip)]
+    diagnostics: Diagnostics,
+    #[serde(skip)]
+    last_selected: Option<u16>,
+    #[serde(skip)]
+    canvas_rect: Rect,
+    #[serde(skip)]
+    palette: CommandPalette,
+    #[serde(skip)]
+    importer: ScriptImporter,
+    #[serde(skip)]
+    should_save: bool,
+    /// Text to put on the clipboard at the end of the frame.
+    #[serde(skip)]
+    clipboard: Option<String>,
+    #[serde(skip)]
+    events: EventTracker,
+    #[serde(skip)]
+    executor: BackgroundExecutor,
+    /// [`Self::run_fingerprint`] of the graph the current run started with.
+    #[serde(skip)]
+    running_fingerprint: u64,
+    /// Iterations to run once the current run finishes.
+    #[serde(skip)]
+    queued_run: Option<u32>,
+    /// Functions to run once the executor is free: edited ones, ones whose
+    /// inputs changed and ones whose run button was pressed.
+    #[serde(skip)]
+    pending_runs: BTreeSet<u16>,
 }
 
 impl Default for TemplateApp {
@@ -64,22 +142,54 @@ impl Default for TemplateApp {
                     param_id: param_id2,
                 },
                 should_be_deleted: false,
+                reroutes: Vec::new(),
+                propagated_at: None,
             }],
             functions: IndexMap::from([(function1.id, function1), (function2.id, function2)]),
             is_cyclic: false,
             last_rect_id: 3,
+            step_count: 10,
+            grid: Grid::default(),
+            link_style: LinkStyle::default(),
+            show_link_values: false,
+            port_hit_size: DEFAULT_PORT_HIT_SIZE,
+            keymap: Keymap::default(),
+            annotations: Annotations::default(),
+            edit_policy: EditPolicy::default(),
+            tests: TestPanel::default(),
+            debugger: Debugger::default(),
+            trace: ExecutionTrace::default(),
+            inspector: ValueInspector::default(),
+            is_dragging_link: false,
+            diagnostics: Diagnostics::default(),
+            last_selected: None,
+            canvas_rect: Rect::ZERO,
+            palette: CommandPalette::default(),
+            importer: ScriptImporter::default(),
+            should_save: false,
+            clipboard: None,
+            events: EventTracker::default(),
+            executor: BackgroundExecutor::default(),
+            running_fingerprint: 0,
+            queued_run: None,
+            pending_runs: BTreeSet::new(),
         }
     }
 }
 
 impl TemplateApp {
     #[no_mangle]
-    pub fn my_alert(self: &Self) {
-        use eframe::web_sys;
-        let window = web_sys::window().expect("No window");
-        let _ = window.alert_with_message(
-            "This alert is called from Rust. You could also get access to DOM.",
-        );
+    pub fn my_alert(&self) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use eframe::web_sys;
+            let window = web_sys::window().expect("No window");
+            let _ = window.alert_with_message(
+                "This alert is called from Rust. You could also get access to DOM.",
+            );
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        log::info!("This alert is called from Rust.");
     }
 
     /// Called once before the first frame.
@@ -94,18 +204,9 @@ impl TemplateApp {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            if let Some(mut storage) = eframe::get_value::<TemplateApp>(storage, eframe::APP_KEY) {
-                if let Some((_, last_rect)) = storage.functions.last() {
-                    storage.last_rect_id = last_rect
-                        .runnable
-                        .name
-                        .chars()
-                        .last()
-                        .unwrap()
-                        .to_digit(10)
-                        .unwrap() as usize
-                        + 1;
-                    return storage;
+            if let Some(storage) = eframe::get_value::<TemplateApp>(storage, eframe::APP_KEY) {
+                if !storage.functions.is_empty() {
+                    return storage.restored();
                 }
             }
         }
@@ -113,14 +214,52 @@ impl TemplateApp {
         Default::default()
     }
 
+    /// Moves the node counter past the persisted one and every `Name #N` on
+    /// the canvas, so new nodes don't repeat a number. Names without one, like
+    /// the `fn` names of imported scripts, don't count.
+    fn restored(mut self) -> Self {
+        self.last_rect_id = self
+            .functions
+            .values()
+            .filter_map(|widget| {
+                let (_, number) = widget.runnable.name.rsplit_once('#')?;
+                number.trim().parse::<usize>().ok()
+            })
+            .map(|number| number + 1)
+            .fold(self.last_rect_id.max(self.functions.len() + 1), usize::max);
+        self
+    }
+
     fn delete_old_links(&mut self) {
         self.links.retain(|link| !link.should_be_deleted);
     }
 
     fn render_links(&mut self, ui: &mut egui::Ui, stroke: egui::Stroke) {
         let collapsed_window_width = 160.0;
+        let obstacles: Vec<Rect> = self
+            .functions
+            .values()
+            .map(|widget| Rect::from_min_size(widget.position, widget.layout_size()))
+            .collect();
+        // Double-click on a link adds a reroute knot there.
+        let mut add_knot_at = ui
+            .input(|i| {
+                i.pointer
+                    .button_double_clicked(egui::PointerButton::Primary)
+            })
+            .then(|| ui.ctx().pointer_latest_pos())
+            .flatten()
+            .filter(|pos| ui.ctx().layer_id_at(*pos) == Some(egui::LayerId::background()))
+            // A double-click on a knot removes it instead, see `render_knots`.
+            .filter(|pos| {
+                !self
+                    .links
+                    .iter()
+                    .flat_map(|link| link.reroutes.iter())
+                    .any(|knot| knot_rect(*knot).contains(*pos))
+            });
 
-        for current_link in self.links.iter_mut() {
+        for (link_index, current_link) in self.links.iter_mut().enumerate() {
             let start_point_widget = self.functions.get(&current_link.start.function_id);
             let end_point_widget = self.functions.get(&current_link.end.function_id);
 
@@ -171,251 +310,243 @@ impl TemplateApp {
                 (false, WidgetMode::Signature) => end_param.unwrap().pos,
             };
 
-            let signum = (end_point.y - start_point.y).signum();
-            match end_point.x - start_point.x {
-                diff if diff <= 0.0 => {
-                    {
-                        let second_point = Pos2 {
-                            x: start_point.x + 150.0,
-                            y: start_point.y,
-                        };
-                        let third_point = Pos2 {
-                            x: start_point.x + 150.0,
-                            y: start_point.y + signum * 150.0,
-                        };
-                        let end_point = Pos2 {
-                            x: start_point.x,
-                            y: start_point.y + signum * 150.0,
-                        };
-
-                        let points: [Pos2; 4] = [start_point, second_point, third_point, end_point];
-                        let curve = CubicBezierShape::from_points_stroke(
-                            points,
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
-                    {
-                        let curve = CubicBezierShape::from_points_stroke(
-                            [
-                                Pos2 {
-                                    x: start_point.x,
-                                    y: start_point.y + signum * 150.0,
-                                },
-                                Pos2 {
-                                    x: (start_point.x + end_point.x) / 2.0,
-                                    y: start_point.y + signum * 150.0,
-                                },
-                                Pos2 {
-                                    x: (start_point.x + end_point.x) / 2.0,
-                                    y: end_point.y - signum * 150.0,
-                                },
-                                Pos2 {
-                                    x: end_point.x,
-                                    y: end_point.y - signum * 150.0,
-                                },
-                            ],
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
-                    {
-                        let start_point = Pos2 {
-                            x: end_point.x,
-                            y: end_point.y - signum * 150.0,
-                        };
-                        let second_point = Pos2 {
-                            x: end_point.x - 150.0,
-                            y: end_point.y - signum * 150.0,
-                        };
-                        let third_point = Pos2 {
-                            x: end_point.x - 150.0,
-                            y: end_point.y,
-                        };
-
-                        let points: [Pos2; 4] = [start_point, second_point, third_point, end_point];
-                        let curve = CubicBezierShape::from_points_stroke(
-                            points,
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
-                }
-                diff if diff > 0.0 && diff < 100.0 => {
-                    {
-                        let second_point = Pos2 {
-                            x: start_point.x + 50.0,
-                            y: start_point.y,
-                        };
-                        let third_point = Pos2 {
-                            x: start_point.x + 50.0,
-                            y: start_point.y + signum * 50.0,
-                        };
-                        // let end_point = Pos2 { x: start_point.x, y: (start_point.y + end_point.y)/2.0 };
-
-                        let points = [start_point, second_point, third_point];
-                        let curve = QuadraticBezierShape::from_points_stroke(
-                            points,
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
-                    {
-                        let curve = CubicBezierShape::from_points_stroke(
-                            [
-                                Pos2 {
-                                    x: start_point.x + 50.0,
-                                    y: start_point.y + signum * 50.0,
-                                },
-                                Pos2 {
-                                    x: (start_point.x + 50.0 + end_point.x) / 2.0,
-                                    y: (start_point.y + end_point.y) / 2.0,
-                                },
-                                Pos2 {
-                                    x: (start_point.x - 50.0 + end_point.x) / 2.0,
-                                    y: (start_point.y + end_point.y) / 2.0,
-                                },
-                                Pos2 {
-                                    x: end_point.x - 50.0,
-                                    y: end_point.y - signum * 50.0,
-                                },
-                            ],
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
-                    {
-                        //let start_point = Pos2 { x: end_point.x, y: (start_point.y + end_point.y)/2.0 };
-                        let second_point = Pos2 {
-                            x: end_point.x - 50.0,
-                            y: end_point.y - signum * 50.0,
-                        };
-                        let third_point = Pos2 {
-                            x: end_point.x - 50.0,
-                            y: end_point.y,
-                        };
-
-                        let points = [second_point, third_point, end_point];
-                        let curve = QuadraticBezierShape::from_points_stroke(
-                            points,
-                            false,
-                            Default::default(),
-                            stroke,
-                        );
-                        ui.painter().add(curve);
-                    }
+            let last_value = start_param.unwrap().last_value.as_ref();
+            let value_color = last_value.and_then(flow::value_color);
+            let is_feedback = end_point_widget.is_delay();
+            let stroke = if !is_feedback
+                && self
+                    .diagnostics
+                    .is_in_cycle(&current_link.start, &current_link.end)
+            {
+                egui::Stroke::new(stroke.width.max(2.0), CYCLE_COLOR)
+            } else if let Some(color) = value_color {
+                egui::Stroke::new(stroke.width, color)
+            } else {
+                stroke
+            };
+
+            let mut points = vec![start_point];
+            points.extend(current_link.reroutes.iter().copied());
+            points.push(end_point);
+            let segments = routing::route(self.link_style, &points, &obstacles);
+            let path: Vec<Pos2> = segments.concat();
+            ui.painter().add(Shape::line(path.clone(), stroke));
+
+            if add_knot_at.is_some_and(|pos| routing::distance_to(&path, pos) <= LINK_HIT_DISTANCE)
+            {
+                let pos = add_knot_at.take().unwrap();
+                let segment_index = segments
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        routing::distance_to(a, pos).total_cmp(&routing::distance_to(b, pos))
+                    })
+                    .map_or(0, |(index, _)| index);
+                current_link.reroutes.insert(segment_index, pos);
+            }
+            render_knots(ui, link_index, &mut current_link.reroutes, stroke);
+
+            if let Some(started_at) = current_link.propagated_at {
+                if flow::paint_pulse(ui.painter(), &path, started_at, stroke.color) {
+                    ui.ctx().request_repaint();
+                } else {
+                    current_link.propagated_at = None;
                 }
-                _ => {
-                    let second_point = Pos2 {
-                        x: (start_point.x + end_point.x) / 2.0,
-                        y: start_point.y,
-                    };
-                    let third_point = Pos2 {
-                        x: (start_point.x + end_point.x) / 2.0,
-                        y: end_point.y,
-                    };
-
-                    let points: [Pos2; 4] = [start_point, second_point, third_point, end_point];
-                    let curve = CubicBezierShape::from_points_stroke(
-                        points,
-                        false,
-                        Default::default(),
-                        stroke,
+            }
+
+            let delete_icon_point = routing::point_at(&path, 0.5);
+            let delete_icon_rect = Rect {
+                min: delete_icon_point - Vec2 { x: 30.0, y: 30.0 },
+                max: delete_icon_point + Vec2 { x: 30.0, y: 30.0 },
+            };
+            let is_hovered = ui
+                .ctx()
+                .pointer_latest_pos()
+                .is_some_and(|pos| delete_icon_rect.contains(pos));
+
+            // The preview makes way for the delete icon on hover.
+            if self.show_link_values && !is_hovered {
+                if let Some(value) = last_value {
+                    flow::paint_preview(
+                        ui.painter(),
+                        delete_icon_point,
+                        flow::value_preview(value),
+                        stroke.color,
                     );
-                    ui.painter().add(curve);
                 }
             }
 
-            if let Some(cursor_pos) = ui.ctx().pointer_latest_pos() {
-                let delete_icon_point = Pos2 {
-                    x: (start_point.x + end_point.x) / 2.0,
-                    y: (start_point.y + end_point.y) / 2.0,
-                };
-                let delete_icon_rect = Rect {
-                    min: delete_icon_point - Vec2 { x: 30.0, y: 30.0 },
-                    max: delete_icon_point + Vec2 { x: 30.0, y: 30.0 },
-                };
-
-                if delete_icon_rect.contains(cursor_pos) {
-                    ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
-                    let delete_icon_response = ui.allocate_ui_at_rect(delete_icon_rect, |ui| {
+            if is_hovered {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                let delete_icon_response =
+                    ui.allocate_new_ui(UiBuilder::new().max_rect(delete_icon_rect), |ui| {
                         let delete_icon = Label::new("❌").selectable(false).sense(Sense::click());
                         ui.with_layout(
                             egui::Layout::centered_and_justified(egui::Direction::TopDown),
                             |ui| ui.add(delete_icon),
                         )
                     });
-                    if delete_icon_response.inner.inner.clicked() {
-                        current_link.should_be_deleted = true;
-                    }
+                if delete_icon_response.inner.inner.clicked() {
+                    current_link.should_be_deleted = true;
                 }
             }
         }
     }
 
     fn create_finished_links(&mut self) {
-        if let Some((start_id, link_start_widget)) = self
+        let mut vertices = self
             .functions
-            .iter()
-            .find(|(_, widget)| widget.has_vertex.is_some())
-        {
-            if let Some((end_id, link_end_widget)) = self
-                .functions
-                .iter()
-                .rev()
-                .find(|(_, widget)| widget.has_vertex.is_some())
-            {
-                if start_id != end_id {
-                    let (link_start, link_end) = if link_start_widget
-                        .runnable
-                        .inputs
-                        .get(&link_start_widget.has_vertex.as_ref().unwrap().param_id)
-                        .is_some()
-                    {
-                        (
-                            link_end_widget.has_vertex.clone().unwrap(),
-                            link_start_widget.has_vertex.clone().unwrap(),
-                        )
-                    } else {
-                        (
-                            link_start_widget.has_vertex.clone().unwrap(),
-                            link_end_widget.has_vertex.clone().unwrap(),
-                        )
-                    };
+            .values()
+            .filter_map(|widget| widget.has_vertex.clone());
 
-                    self.links.push(Link {
-                        start: link_start,
-                        end: link_end,
-                        should_be_deleted: false,
-                    });
+        if let (Some(first), Some(second)) = (vertices.next(), vertices.next()) {
+            if let Err(message) = self.add_link(first, second) {
+                self.diagnostics.message = Some(message);
+            }
 
-                    if let Some((_, link_start_widget)) = self
-                        .functions
-                        .iter_mut()
-                        .find(|(_, widget)| widget.has_vertex.is_some())
-                    {
-                        link_start_widget.has_vertex.take();
-                    }
+            for widget in self.functions.values_mut() {
+                widget.has_vertex.take();
+            }
+        }
+    }
 
-                    if let Some((_, link_end)) = self
-                        .functions
-                        .iter_mut()
-                        .rev()
-                        .find(|(_, widget)| widget.has_vertex.is_some())
-                    {
-                        link_end.has_vertex.take();
-                    }
-                }
+    fn is_output(&self, vertex: &LinkVertex) -> Option<bool> {
+        let widget = self.functions.get(&vertex.function_id)?;
+        if widget.runnable.outputs.contains_key(&vertex.param_id) {
+            Some(true)
+        } else if widget.runnable.inputs.contains_key(&vertex.param_id) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Links an output to an input given in any order. An input has at most one
+    /// source, so an existing link into it is replaced.
+    fn add_link(&mut self, first: LinkVertex, second: LinkVertex) -> Result<(), String> {
+        if first.function_id == second.function_id {
+            return Err("A function can't be linked to itself".to_owned());
+        }
+        let (start, end) = match (self.is_output(&first), self.is_output(&second)) {
+            (Some(true), Some(false)) => (first, second),
+            (Some(false), Some(true)) => (second, first),
+            (Some(_), Some(_)) => {
+                return Err("Only an output and an input can be linked".to_owned())
+            }
+            _ => return Err("Linked parameter doesn't exist".to_owned()),
+        };
+
+        let is_duplicate = self.links.iter().any(|link| {
+            !link.should_be_deleted
+                && link.start.function_id == start.function_id
+                && link.start.param_id == start.param_id
+                && link.end.function_id == end.function_id
+                && link.end.param_id == end.param_id
+        });
+        if is_duplicate {
+            return Err("These parameters are already linked".to_owned());
+        }
+        if !self.is_delay(end.function_id) && self.has_path(end.function_id, start.function_id) {
+            let name = |id: u16| self.functions[&id].runnable.name.clone();
+            return Err(format!(
+                "Linking {} to {} would create a cycle",
+                name(start.function_id),
+                name(end.function_id)
+            ));
+        }
+
+        for link in self.links.iter_mut() {
+            if link.end.function_id == end.function_id && link.end.param_id == end.param_id {
+                link.should_be_deleted = true;
+            }
+        }
+        self.links.push(Link {
+            start,
+            end,
+            should_be_deleted: false,
+            reroutes: Vec::new(),
+            propagated_at: None,
+        });
+        Ok(())
+    }
+
+    /// The parameter whose port is under `pos`, if its widget shows ports.
+    fn port_at(&self, pos: Pos2) -> Option<LinkVertex> {
+        let hit_radius = PORT_HIT_RADIUS.max(self.port_hit_size / 2.0);
+        self.functions
+            .values()
+            .filter(|widget| !widget.is_collapsed && widget.mode == WidgetMode::Signature)
+            .find_map(|widget| {
+                widget
+                    .runnable
+                    .inputs
+                    .iter()
+                    .chain(widget.runnable.outputs.iter())
+                    .find(|(_, param)| param.pos.distance(pos) <= hit_radius)
+                    .map(|(param_id, _)| LinkVertex {
+                        function_id: widget.id,
+                        param_id: *param_id,
+                    })
+            })
+    }
+
+    /// Starts dragging a link from a port. Dragging from an input that already
+    /// has a source picks up that link so its end can be dropped elsewhere.
+    fn begin_link_drag(&mut self) {
+        let Some(dragged_id) = self
+            .functions
+            .iter_mut()
+            .find(|(_, widget)| widget.link_drag_started)
+            .map(|(id, widget)| {
+                widget.link_drag_started = false;
+                *id
+            })
+        else {
+            return;
+        };
+
+        for (id, widget) in self.functions.iter_mut() {
+            if *id != dragged_id {
+                widget.has_vertex.take();
+            }
+        }
+        self.is_dragging_link = true;
+
+        let Some(vertex) = self.functions[&dragged_id].has_vertex.clone() else {
+            return;
+        };
+        if let Some(link) = self.links.iter_mut().find(|link| {
+            !link.should_be_deleted
+                && link.end.function_id == vertex.function_id
+                && link.end.param_id == vertex.param_id
+        }) {
+            link.should_be_deleted = true;
+            let source = link.start.clone();
+            self.functions[&dragged_id].has_vertex.take();
+            if let Some(source_widget) = self.functions.get_mut(&source.function_id) {
+                source_widget.has_vertex = Some(source);
+            }
+        }
+    }
+
+    /// Drops the dragged link onto the port under the pointer, or discards it.
+    fn finish_link_drag(&mut self, ui: &egui::Ui) {
+        if !self.is_dragging_link || ui.input(|i| i.pointer.primary_down()) {
+            return;
+        }
+        self.is_dragging_link = false;
+
+        let start = self
+            .functions
+            .values_mut()
+            .find_map(|widget| widget.has_vertex.take());
+        let target = ui
+            .ctx()
+            .pointer_latest_pos()
+            .and_then(|pos| self.port_at(pos));
+        if let (Some(start), Some(target)) = (start, target) {
+            if let Err(message) = self.add_link(start, target) {
+                self.diagnostics.message = Some(message);
             }
         }
     }
@@ -426,7 +557,15 @@ impl TemplateApp {
             .iter()
             .find(|(_, widget)| widget.has_vertex.is_some())
         {
-            if let Some(link_end) = ui.ctx().pointer_latest_pos() {
+            // While linking with the keyboard the link follows the focused port.
+            let focused_port = self.functions.values().find_map(|widget| {
+                let param_id = widget.focused_port?;
+                Some(widget.runnable.get_param_by_vertex(&LinkVertex {
+                    function_id: widget.id,
+                    param_id,
+                }))
+            });
+            if let Some(link_end) = focused_port.or(ui.ctx().pointer_latest_pos()) {
                 let link_start = link_start_widget.has_vertex.clone().unwrap();
                 let link_start_pos = link_start_widget.runnable.get_param_by_vertex(&link_start);
 
@@ -448,18 +587,11 @@ impl TemplateApp {
         }
     }
 
-    fn cancel_link_if_esc(&mut self, ui: &mut egui::Ui) {
-        if let Some((_, link_start_widget)) = self
-            .functions
-            .iter_mut()
-            .find(|(_, widget)| widget.has_vertex.is_some())
-        {
-            ui.input(|i| {
-                if i.key_pressed(Key::Escape) {
-                    link_start_widget.has_vertex.take();
-                }
-            });
+    fn cancel_link(&mut self) {
+        for widget in self.functions.values_mut() {
+            widget.has_vertex.take();
         }
+        self.is_dragging_link = false;
     }
 
     fn render_side_panel(&mut self, ctx: &egui::Context) {
@@ -475,34 +607,145 @@ impl TemplateApp {
                     .rounding(5.0),
                 );
                 if btn_response.clicked() {
-                    let new_fw = FunctionWidget::default_with_pos(
-                        Pos2 { x: 0.0, y: 0.0 },
-                        format!("Function #{}", self.last_rect_id),
-                    );
-                    self.functions.insert(new_fw.id, new_fw);
-                    self.last_rect_id += 1;
+                    self.execute(Command::AddFunction);
                 }
                 ui.add_space(5.0);
-                let btn_resp = ui.add_enabled(
-                    !self.is_cyclic,
-                    egui::Button::new("▶ Run all").rounding(5.0),
-                );
+                if ui
+                    .add(egui::Button::new("⏱ Add delay").rounding(5.0))
+                    .clicked()
+                {
+                    self.execute(Command::AddDelay);
+                }
+                ui.add_space(5.0);
+                if ui
+                    .add(egui::Button::new("▦ Auto layout").rounding(5.0))
+                    .clicked()
+                {
+                    self.execute(Command::AutoLayout);
+                }
+                let selected_count = self.functions.values().filter(|w| w.is_selected).count();
+                if ui
+                    .add_enabled(
+                        selected_count > 1,
+                        egui::Button::new("▤ Tidy selection").rounding(5.0),
+                    )
+                    .clicked()
+                {
+                    self.execute(Command::TidySelection);
+                }
+                ui.add_space(5.0);
+                if ui
+                    .add(egui::Button::new("🗖 Add comment").rounding(5.0))
+                    .on_hover_text("Frames the selected nodes")
+                    .clicked()
+                {
+                    self.execute(Command::AddComment);
+                }
+                if ui
+                    .add(egui::Button::new("🗊 Add note").rounding(5.0))
+                    .clicked()
+                {
+                    self.execute(Command::AddNote);
+                }
+                if ui
+                    .add(egui::Button::new("📥 Import script").rounding(5.0))
+                    .on_hover_text("Turns the fns of a rhai script into functions")
+                    .clicked()
+                {
+                    self.execute(Command::ImportScript);
+                }
+                ui.add_space(5.0);
+                ui.collapsing("Arrange", |ui| {
+                    self.grid.ui(ui);
+                    egui::ComboBox::from_label("Links")
+                        .selected_text(self.link_style.name())
+                        .width(70.0)
+                        .show_ui(ui, |ui| {
+                            for style in LinkStyle::ALL {
+                                ui.selectable_value(&mut self.link_style, style, style.name());
+                            }
+                        });
+                    ui.checkbox(&mut self.show_link_values, "Show values on links");
+                    ui.separator();
+                    self.render_align_buttons(ui, selected_count);
+                });
+                ui.add_space(5.0);
+                let can_run = !self.is_cyclic && !self.is_graph_running();
+                let btn_resp = ui
+                    .add_enabled(can_run, egui::Button::new("▶ Run all").rounding(5.0))
+                    .on_disabled_hover_text(if self.is_cyclic {
+                        "Links form a cycle, see Diagnostics"
+                    } else {
+                        "Waiting for the current run"
+                    });
                 if btn_resp.clicked() {
-                    let g: StableGraph<(), (), Directed, u32> =
-                        StableGraph::from_edges(self.links.iter());
-
-                    let sorted = toposort(&g, None);
-                    if let Ok(sorted) = sorted {
-                        for ele in sorted {
-                            let function_widget =
-                                self.functions.get_mut(&(ele.index() as u16)).unwrap();
-                            function_widget.runnable.run(&function_widget.engine);
-
-                            self.update_last_values();
-                        }
+                    self.execute(Command::RunAll);
+                }
+                ui.horizontal(|ui| {
+                    let step_btn = ui
+                        .add_enabled(can_run, egui::Button::new("⟳ Step").rounding(5.0))
+                        .on_hover_text("Run the whole graph this many times");
+                    ui.add(egui::DragValue::new(&mut self.step_count).range(1..=10_000));
+                    if step_btn.clicked() {
+                        self.execute(Command::StepN);
                     }
+                });
+                if let Some(run) = self.executor.running() {
+                    ui.add(
+                        egui::ProgressBar::new(run.fraction())
+                            .text(format!("{} / {} runs", run.completed, run.total))
+                            .animate(true),
+                    );
+                }
+                egui::ComboBox::from_label("On edit while running")
+                    .selected_text(self.edit_policy.name())
+                    .width(70.0)
+                    .show_ui(ui, |ui| {
+                        for policy in EditPolicy::ALL {
+                            ui.selectable_value(&mut self.edit_policy, policy, policy.name());
+                        }
+                    });
+                if ui
+                    .add(egui::Button::new("✔ Tests").rounding(5.0))
+                    .on_hover_text("Saved inputs and the outputs they must give")
+                    .clicked()
+                {
+                    self.tests.is_open = true;
                 }
                 ui.add_space(5.0);
+                self.render_debug_controls(ui);
+                ui.add_space(5.0);
+                if ui
+                    .add(egui::Button::new("⌨ Shortcuts").rounding(5.0))
+                    .clicked()
+                {
+                    self.execute(Command::ShowShortcuts);
+                }
+                ui.collapsing("Accessibility", |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut self.port_hit_size, 5.0..=32.0).text("Port size"),
+                    );
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Tab or arrows move between ports, Enter starts and finishes a link, \
+                             Esc cancels it. {} moves to the next node.",
+                            self.keymap.format(ctx, Action::NextNode)
+                        ))
+                        .weak()
+                        .small(),
+                    );
+                });
+                ui.add_space(5.0);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} to search, {} for commands",
+                        self.keymap.format(ctx, Action::OpenSearch),
+                        self.keymap.format(ctx, Action::OpenCommands)
+                    ))
+                    .weak()
+                    .small(),
+                );
+                ui.add_space(5.0);
                 let alert_btn_response = ui.button("Call alert from Rust");
                 if alert_btn_response.clicked() {
                     self.my_alert();
@@ -510,10 +753,935 @@ impl TemplateApp {
             });
     }
 
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::AddFunction => {
+                let new_fw = FunctionWidget::default_with_pos(
+                    self.new_node_position(),
+                    format!("Function #{}", self.last_rect_id),
+                );
+                self.add_widget(new_fw);
+            }
+            Command::AddDelay => {
+                let delay = FunctionWidget::delay_with_pos(
+                    self.new_node_position(),
+                    format!("Delay #{}", self.last_rect_id),
+                );
+                self.add_widget(delay);
+            }
+            Command::AddComment => {
+                let content = self
+                    .selected_rects()
+                    .into_iter()
+                    .map(|(_, rect)| rect)
+                    .reduce(|a, b| a.union(b))
+                    .unwrap_or(Rect::from_min_size(
+                        self.new_node_position(),
+                        Vec2 { x: 200.0, y: 100.0 },
+                    ));
+                self.annotations.add_comment(content);
+            }
+            Command::AddNote => {
+                let position = self.new_node_position();
+                self.annotations.add_note(position);
+            }
+            Command::RunAll => self.run_graph(1),
+            Command::StepN => self.run_graph(self.step_count),
+            Command::AutoLayout => self.auto_layout(),
+            Command::TidySelection => self.tidy_selection(),
+            Command::ToggleGrid => self.grid.is_visible = !self.grid.is_visible,
+            Command::ToggleSnap => self.grid.snap = !self.grid.snap,
+            Command::ToggleLinkValues => self.show_link_values = !self.show_link_values,
+            Command::StartDebugging => {
+                if !self.is_cyclic && !self.debugger.is_active() {
+                    if let Some(order) = self.execution_order() {
+                        self.debugger.start(order);
+                    }
+                }
+            }
+            Command::DebugStep => {
+                if self.debugger.is_active() {
+                    self.debug_step();
+                }
+            }
+            Command::DebugContinue => {
+                if self.debugger.is_active() {
+                    self.debug_continue();
+                }
+            }
+            Command::StopDebugging => self.debugger.stop(),
+            Command::ClearTrace => self.trace.clear(),
+            Command::Save => self.should_save = true,
+            Command::ShowShortcuts => self.keymap.is_open = true,
+            Command::CopyProject => {
+                self.clipboard = Some(self.project().to_json());
+                self.events.mark_saved();
+            }
+            Command::ExportScript => match script::export(&self.graph()) {
+                Some(script) => files::save("graph.rhai", &script),
+                None => log::error!("Can't export a graph whose links form a cycle"),
+            },
+            Command::ImportScript => self.importer.is_open = true,
+            Command::ExportDot => files::save("graph.dot", &diagram::dot(&self.graph())),
+            Command::ExportMermaid => files::save("graph.mmd", &diagram::mermaid(&self.graph())),
+            Command::ExportSvg => files::save(
+                "graph.svg",
+                &diagram::svg(&self.graph(), &self.placements()),
+            ),
+            Command::RunTests => {
+                self.tests.is_open = true;
+                let graph = self.graph();
+                self.tests.run_all(&graph);
+            }
+        }
+    }
+
+    fn palette_entries(&self, mode: PaletteMode) -> Vec<PaletteEntry> {
+        let mut entries = Vec::new();
+        if mode == PaletteMode::Everything {
+            entries.extend(self.functions.iter().map(|(id, widget)| PaletteEntry {
+                item: PaletteItem::Node(*id),
+                label: widget.runnable.name.clone(),
+                detail: "node",
+            }));
+            entries.extend(
+                library::TEMPLATES
+                    .iter()
+                    .enumerate()
+                    .map(|(index, template)| PaletteEntry {
+                        item: PaletteItem::Template(index),
+                        label: template.name.to_owned(),
+                        detail: template.description,
+                    }),
+            );
+        }
+        entries.extend(Command::ALL.into_iter().map(|command| PaletteEntry {
+            item: PaletteItem::Command(command),
+            label: command.name().to_owned(),
+            detail: "command",
+        }));
+        entries
+    }
+
+    /// Runs the canvas actions whose shortcuts were pressed. The open palette
+    /// keeps the keyboard to itself.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.palette.mode().is_some() {
+            return;
+        }
+        let is_linking = self
+            .functions
+            .values()
+            .any(|widget| widget.has_vertex.is_some());
+        for action in self.keymap.pressed_actions(ctx, is_linking) {
+            match action {
+                Action::Command(command) => self.execute(command),
+                Action::OpenSearch => self.palette.open(PaletteMode::Everything),
+                Action::OpenCommands => self.palette.open(PaletteMode::CommandsOnly),
+                Action::NextNode => self.focus_adjacent_node(false),
+                Action::PreviousNode => self.focus_adjacent_node(true),
+                Action::ToggleWidgetMode => self.toggle_widget_mode(ctx),
+                Action::CancelLink => self.cancel_link(),
+                Action::CommitEdit | Action::CancelEdit | Action::TriggerCompletion => {}
+            }
+        }
+    }
+
+    /// Switches the node under the pointer, or else the selected ones, between
+    /// signature and code.
+    fn toggle_widget_mode(&mut self, ctx: &egui::Context) {
+        let pointer = ctx.pointer_latest_pos();
+        let hovered = self.functions.values().position(|widget| {
+            pointer.is_some_and(|pos| {
+                Rect::from_min_size(widget.position, widget.layout_size()).contains(pos)
+            })
+        });
+        for (index, widget) in self.functions.values_mut().enumerate() {
+            let should_toggle = match hovered {
+                Some(hovered) => index == hovered,
+                None => widget.is_selected,
+            };
+            if should_toggle && !widget.is_delay() {
+                widget.mode = if widget.mode == WidgetMode::Signature {
+                    WidgetMode::Code
+                } else {
+                    WidgetMode::Signature
+                };
+            }
+        }
+    }
+
+    fn show_palette(&mut self, ctx: &egui::Context) {
+        let Some(mode) = self.palette.mode() else {
+            return;
+        };
+
+        let entries = self.palette_entries(mode);
+        match self.palette.show(ctx, entries) {
+            Some(PaletteItem::Node(id)) => self.focus_node(id),
+            Some(PaletteItem::Template(index)) => {
+                let template = &library::TEMPLATES[index];
+                let runnable =
+                    template.runnable(format!("{} #{}", template.name, self.last_rect_id));
+                let widget = FunctionWidget::new(runnable, self.new_node_position(), true, false);
+                self.add_widget(widget);
+            }
+            Some(PaletteItem::Command(command)) => self.execute(command),
+            None => {}
+        }
+    }
+
+    /// Moves keyboard focus to the node after (or before) the selected one.
+    fn focus_adjacent_node(&mut self, previous: bool) {
+        if self.functions.is_empty() {
+            return;
+        }
+
+        let count = self.functions.len();
+        let index = match self
+            .last_selected
+            .and_then(|id| self.functions.get_index_of(&id))
+        {
+            Some(index) if previous => (index + count - 1) % count,
+            Some(index) => (index + 1) % count,
+            None if previous => count - 1,
+            None => 0,
+        };
+        let id = *self.functions.get_index(index).unwrap().0;
+        self.focus_node(id);
+    }
+
+    /// Pans the canvas so the node is in the middle, selects it and brings it to the front.
+    fn focus_node(&mut self, id: u16) {
+        let Some(widget) = self.functions.get(&id) else {
+            return;
+        };
+        let rect = Rect::from_min_size(widget.position, widget.layout_size());
+        self.pan_by(self.canvas_rect.center() - rect.center());
+
+        for (widget_id, widget) in self.functions.iter_mut() {
+            widget.is_selected = *widget_id == id;
+            widget.focus_request = *widget_id == id;
+        }
+        self.last_selected = Some(id);
+    }
+
+    /// Moves everything on the canvas by `delta`.
+    fn pan_by(&mut self, delta: Vec2) {
+        for widget in self.functions.values_mut() {
+            let position = widget.position + delta;
+            widget.move_to(position);
+        }
+        for link in self.links.iter_mut() {
+            for knot in link.reroutes.iter_mut() {
+                *knot += delta;
+            }
+        }
+        self.annotations.translate(delta);
+    }
+
+    /// Steps the whole graph `iterations` times off the UI thread, telling the
+    /// host when it starts. The results are applied in [`Self::poll_executor`].
+    fn run_graph(&mut self, iterations: u32) {
+        if self.is_cyclic || self.is_graph_running() {
+            return;
+        }
+        self.events.push(GraphEvent::RunStarted { iterations });
+        self.running_fingerprint = self.run_fingerprint();
+        // Every function runs, so nothing is left to re-run.
+        self.pending_runs.clear();
+        self.executor
+            .start(self.graph(), iterations, RunScope::Graph);
+    }
+
+    /// Whether "Run all" or "Step" is running. Re-runs after edits and
+    /// debugger steps don't count, a graph run replaces them.
+    fn is_graph_running(&self) -> bool {
+        self.executor
+            .running()
+            .is_some_and(|run| run.scope == RunScope::Graph)
+    }
+
+    /// Runs the pending functions and everything downstream of them off the
+    /// UI thread, once nothing else runs. Delays are left alone, they only
+    /// advance when the whole graph is stepped.
+    fn start_pending_runs(&mut self) {
+        if self.pending_runs.is_empty() || self.executor.is_running() || self.is_cyclic {
+            return;
+        }
+        let Some(order) = self.execution_order() else {
+            return;
+        };
+        let mut affected = std::mem::take(&mut self.pending_runs);
+        // The order is topological, so one pass reaches everything downstream.
+        for function_id in order.iter() {
+            let is_downstream = self.scheduling_links().any(|link| {
+                link.end.function_id == *function_id && affected.contains(&link.start.function_id)
+            });
+            if is_downstream {
+                affected.insert(*function_id);
+            }
+        }
+        let ids: Vec<u16> = order
+            .into_iter()
+            .filter(|id| affected.contains(id) && !self.is_delay(*id))
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        // Edits made from now on need another run.
+        for id in ids.iter() {
+            if let Some(widget) = self.functions.get_mut(id) {
+                widget.is_dirty = false;
+            }
+        }
+        self.executor.start(
+            self.graph(),
+            1,
+            RunScope::Functions {
+                ids,
+                latch_delays: false,
+            },
+        );
+    }
+
+    /// Hash of what a run depends on: code, parameters, links and the values
+    /// of inputs nothing is linked to.
+    fn run_fingerprint(&self) -> u64 {
+        let graph = self.graph();
+        let inputs: Vec<(u16, u16, String)> = graph
+            .inputs()
+            .into_iter()
+            .map(|port| {
+                let value = port.value.map(|value| value.to_string());
+                (port.function_id, port.param_id, value.unwrap_or_default())
+            })
+            .collect();
+        let functions: Vec<_> = graph
+            .functions
+            .iter()
+            .map(|(id, runnable)| {
+                let params: Vec<(u16, &str)> = runnable
+                    .inputs
+                    .iter()
+                    .chain(runnable.outputs.iter())
+                    .map(|(param_id, param)| (*param_id, param.param_name.as_str()))
+                    .collect();
+                (*id, runnable.code.as_str(), params)
+            })
+            .collect();
+        let links: Vec<(u16, u16, u16, u16)> = graph
+            .links
+            .iter()
+            .map(|link| {
+                (
+                    link.start.function_id,
+                    link.start.param_id,
+                    link.end.function_id,
+                    link.end.param_id,
+                )
+            })
+            .collect();
+        events::fingerprint((functions, links, inputs))
+    }
+
+    /// Restarts or queues the run when the graph was edited since it started.
+    fn handle_edits_during_run(&mut self) {
+        let Some(run) = self
+            .executor
+            .running()
+            .filter(|run| run.scope == RunScope::Graph)
+        else {
+            return;
+        };
+        let fingerprint = self.run_fingerprint();
+        if fingerprint == self.running_fingerprint || self.is_cyclic {
+            return;
+        }
+        self.running_fingerprint = fingerprint;
+        match self.edit_policy {
+            EditPolicy::Restart => {
+                let iterations = run.iterations;
+                self.pending_runs.clear();
+                self.executor
+                    .start(self.graph(), iterations, RunScope::Graph);
+            }
+            EditPolicy::Queue => self.queued_run = Some(run.iterations),
+        }
+    }
+
+    /// Applies runs the executor finished since the last frame.
+    fn poll_executor(&mut self, ctx: &egui::Context) {
+        for (run, message) in self.executor.poll(ctx) {
+            match message {
+                ExecutionMessage::Finished { graph, trace, .. } => {
+                    self.apply_run(run, *graph, trace);
+                }
+                ExecutionMessage::Failed { message, .. } => {
+                    log::error!("Run failed: {message}");
+                    self.events.push(GraphEvent::RunFinished {
+                        iterations: run.iterations,
+                        duration: trace::now() - run.started_at,
+                        errors: 1,
+                    });
+                }
+                ExecutionMessage::Progress { .. } => {}
+            }
+        }
+        if !self.executor.is_running() {
+            if let Some(iterations) = self.queued_run.take() {
+                self.run_graph(iterations);
+            }
+        }
+
+        let executing = self
+            .executor
+            .running()
+            .map(|run| run.executing.clone())
+            .unwrap_or_default();
+        for (id, widget) in self.functions.iter_mut() {
+            widget.is_executing = executing.contains(id);
+        }
+    }
+
+    /// Takes the values and trace of a finished run over into the editor.
+    fn apply_run(&mut self, run: RunState, graph: Graph, trace: Vec<TraceEntry>) {
+        let now = trace::now();
+        for link in self.links.iter_mut() {
+            let old_value = self
+                .functions
+                .get(&link.end.function_id)
+                .and_then(|widget| widget.runnable.inputs.get(&link.end.param_id))
+                .and_then(|param| param.last_value.as_ref());
+            let new_value = graph
+                .functions
+                .get(&link.end.function_id)
+                .and_then(|runnable| runnable.inputs.get(&link.end.param_id))
+                .and_then(|param| param.last_value.as_ref());
+            if let Some(new_value) = new_value {
+                if old_value.map_or(true, |old_value| !is_same_value(old_value, new_value)) {
+                    link.propagated_at = Some(now);
+                }
+            }
+        }
+
+        for (function_id, runnable) in graph.functions {
+            let Some(widget) = self.functions.get_mut(&function_id) else {
+                continue;
+            };
+            // Runs only change linked inputs. The others keep what was typed meanwhile.
+            let linked_inputs = runnable.inputs.into_iter().filter(|(param_id, _)| {
+                self.links.iter().any(|link| {
+                    link.end.function_id == function_id && link.end.param_id == *param_id
+                })
+            });
+            for (param_id, param) in linked_inputs.chain(runnable.outputs) {
+                let current = widget
+                    .runnable
+                    .inputs
+                    .get_mut(&param_id)
+                    .or(widget.runnable.outputs.get_mut(&param_id));
+                if let (Some(current), Some(value)) = (current, param.last_value) {
+                    current.update_last_value(value);
+                }
+            }
+            widget.runnable.state = runnable.state;
+            if widget.runnable.code == runnable.code {
+                widget.is_dirty = false;
+            }
+        }
+
+        let errors = trace.iter().filter(|entry| entry.error.is_some()).count();
+        for mut entry in trace {
+            entry.started_at += run.started_at;
+            if let Some(widget) = self.functions.get_mut(&entry.function_id) {
+                widget.pending_trace.push(entry);
+            }
+        }
+        if run.scope == RunScope::Graph {
+            self.events.push(GraphEvent::RunFinished {
+                iterations: run.iterations,
+                duration: now - run.started_at,
+                errors,
+            });
+        }
+    }
+
+    fn add_widget(&mut self, widget: FunctionWidget) {
+        for other in self.functions.values_mut() {
+            other.is_selected = false;
+        }
+        let mut widget = widget;
+        widget.is_selected = true;
+        self.last_selected = Some(widget.id);
+        self.functions.insert(widget.id, widget);
+        self.last_rect_id += 1;
+    }
+
+    /// Adds the functions and links of `graph` as new, selected nodes where a
+    /// new node would go, arranged the way its layout says or laid out anew.
+    fn import_graph(&mut self, graph: Graph) {
+        let origin = self.new_node_position();
+        let layout = graph.layout;
+        let offset = layout
+            .positions
+            .values()
+            .copied()
+            .reduce(|a, b| a.min(b))
+            .map_or(Vec2::ZERO, |corner| origin - corner);
+        for other in self.functions.values_mut() {
+            other.is_selected = false;
+        }
+        let mut ids = HashMap::new();
+        for (function_id, runnable) in graph.functions {
+            let position = layout
+                .positions
+                .get(&function_id)
+                .map_or(origin, |position| *position + offset);
+            let mut widget = FunctionWidget::from_runnable(runnable, position);
+            while self.functions.contains_key(&widget.id) {
+                widget.id = fastrand::u16(..);
+            }
+            widget.is_selected = true;
+            ids.insert(function_id, widget.id);
+            self.last_selected = Some(widget.id);
+            self.functions.insert(widget.id, widget);
+        }
+        for link in graph.links {
+            let (Some(&start), Some(&end)) = (
+                ids.get(&link.start.function_id),
+                ids.get(&link.end.function_id),
+            ) else {
+                continue;
+            };
+            let reroutes = layout
+                .reroutes
+                .iter()
+                .find(|reroute| {
+                    reroute.start.function_id == link.start.function_id
+                        && reroute.start.param_id == link.start.param_id
+                        && reroute.end.function_id == link.end.function_id
+                        && reroute.end.param_id == link.end.param_id
+                })
+                .map(|reroute| reroute.points.iter().map(|point| *point + offset).collect())
+                .unwrap_or_default();
+            self.links.push(Link {
+                start: LinkVertex {
+                    function_id: start,
+                    param_id: link.start.param_id,
+                },
+                end: LinkVertex {
+                    function_id: end,
+                    param_id: link.end.param_id,
+                },
+                should_be_deleted: false,
+                reroutes,
+                propagated_at: None,
+            });
+        }
+        if layout.positions.is_empty() {
+            self.tidy_selection();
+        }
+        self.annotations.add_from_layout(&layout, offset);
+    }
+
+    /// Imports the pasted script, and the `.rhai` scripts and `.json` projects
+    /// dropped on the window.
+    fn import_scripts(&mut self, ctx: &egui::Context) {
+        if let Some(imported) = self.importer.show(ctx) {
+            self.import_script("the script", imported);
+        }
+        for (name, source) in files::dropped(ctx, ".rhai") {
+            match script::import(&source) {
+                Ok(imported) => self.import_script(&name, imported),
+                Err(err) => self.diagnostics.message = Some(format!("Can't import {name}: {err}")),
+            }
+        }
+        for (name, json) in files::dropped(ctx, ".json") {
+            match serde_json::from_str::<Graph>(&json) {
+                Ok(graph) => self.import_graph(graph),
+                Err(err) => self.diagnostics.message = Some(format!("Can't open {name}: {err}")),
+            }
+        }
+    }
+
+    fn import_script(&mut self, name: &str, imported: Imported) {
+        self.import_graph(imported.graph);
+        if !imported.warnings.is_empty() {
+            self.diagnostics.message = Some(format!(
+                "Parts of {name} weren't imported:\n{}",
+                imported.warnings.join("\n")
+            ));
+        }
+    }
+
+    /// Right of the last selected node, or staggered from the canvas corner.
+    fn new_node_position(&self) -> Pos2 {
+        if let Some(last) = self.last_selected.and_then(|id| self.functions.get(&id)) {
+            return last.position
+                + Vec2 {
+                    x: last.layout_size().x + NEW_NODE_GAP,
+                    y: 0.0,
+                };
+        }
+        let offset = (self.functions.len() % 10) as f32 * 20.0;
+        self.canvas_rect.min
+            + Vec2 {
+                x: offset,
+                y: offset,
+            }
+    }
+
+    fn layout_edges(&self) -> Vec<(u16, u16)> {
+        self.scheduling_links()
+            .map(|link| (link.start.function_id, link.end.function_id))
+            .collect()
+    }
+
+    fn auto_layout(&mut self) {
+        let nodes: Vec<(u16, Vec2)> = self
+            .functions
+            .iter()
+            .map(|(id, widget)| (*id, widget.layout_size()))
+            .collect();
+        let origin = self.canvas_rect.min + Vec2 { x: 20.0, y: 20.0 };
+        self.apply_layout(layered_layout(&nodes, &self.layout_edges(), origin));
+    }
+
+    /// Lays out only the selected nodes, starting from their top left corner.
+    fn tidy_selection(&mut self) {
+        let nodes: Vec<(u16, Vec2)> = self
+            .functions
+            .iter()
+            .filter(|(_, widget)| widget.is_selected)
+            .map(|(id, widget)| (*id, widget.layout_size()))
+            .collect();
+        let origin = self
+            .functions
+            .values()
+            .filter(|widget| widget.is_selected)
+            .map(|widget| widget.position)
+            .reduce(|a, b| a.min(b))
+            .unwrap_or(self.canvas_rect.min);
+        self.apply_layout(layered_layout(&nodes, &self.layout_edges(), origin));
+    }
+
+    fn apply_layout(&mut self, positions: HashMap<u16, Pos2>) {
+        for (id, position) in positions {
+            if let Some(widget) = self.functions.get_mut(&id) {
+                widget.move_to(position);
+            }
+        }
+    }
+
+    fn render_align_buttons(&mut self, ui: &mut egui::Ui, selected_count: usize) {
+        ui.add_enabled_ui(selected_count > 1, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                let alignments = [
+                    ("⏴", "Align left", Alignment::Left),
+                    ("⏵", "Align right", Alignment::Right),
+                    ("⏶", "Align top", Alignment::Top),
+                    ("⏷", "Align bottom", Alignment::Bottom),
+                    ("↔", "Center horizontally", Alignment::CenterX),
+                    ("↕", "Center vertically", Alignment::CenterY),
+                ];
+                for (icon, hint, alignment) in alignments {
+                    if ui.button(icon).on_hover_text(hint).clicked() {
+                        let positions = layout::align(&self.selected_rects(), alignment);
+                        self.apply_layout(positions);
+                    }
+                }
+            });
+        });
+        ui.add_enabled_ui(selected_count > 2, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("⇹").on_hover_text("Space horizontally").clicked() {
+                    let positions = layout::distribute(&self.selected_rects(), true);
+                    self.apply_layout(positions);
+                }
+                if ui.button("⇳").on_hover_text("Space vertically").clicked() {
+                    let positions = layout::distribute(&self.selected_rects(), false);
+                    self.apply_layout(positions);
+                }
+            });
+        });
+    }
+
+    fn selected_rects(&self) -> Vec<(u16, Rect)> {
+        self.functions
+            .iter()
+            .filter(|(_, widget)| widget.is_selected)
+            .map(|(id, widget)| {
+                (
+                    *id,
+                    Rect::from_min_size(widget.position, widget.layout_size()),
+                )
+            })
+            .collect()
+    }
+
+    /// Keeps dragged windows on the grid. The target is computed from where the
+    /// drag started, so small pointer moves aren't lost to rounding.
+    fn snap_dragged_widgets(&mut self, ui: &egui::Ui) {
+        if !self.grid.snap {
+            return;
+        }
+        let (Some(press_origin), Some(pointer)) =
+            ui.input(|i| (i.pointer.press_origin(), i.pointer.latest_pos()))
+        else {
+            return;
+        };
+        for widget in self.functions.values_mut() {
+            if let Some(start) = widget.drag_start_position {
+                let target = self.grid.snap(start + (pointer - press_origin));
+                if target != widget.position {
+                    widget.move_to(target);
+                }
+            }
+        }
+    }
+
+    /// Click selects a node, Shift/Ctrl+click toggles it, click on empty canvas clears the selection.
+    fn update_selection(&mut self, ui: &egui::Ui) {
+        let modifiers = ui.input(|i| i.modifiers);
+        let clicked_id = self
+            .functions
+            .iter()
+            .find(|(_, widget)| widget.was_clicked)
+            .map(|(id, _)| *id);
+
+        if let Some(clicked_id) = clicked_id {
+            let is_toggle = modifiers.shift || modifiers.command;
+            for (id, widget) in self.functions.iter_mut() {
+                if *id == clicked_id {
+                    widget.is_selected = !(is_toggle && widget.is_selected);
+                } else if !is_toggle {
+                    widget.is_selected = false;
+                }
+            }
+            self.last_selected = Some(clicked_id);
+            return;
+        }
+
+        let pressed_on_canvas = ui.input(|i| i.pointer.primary_pressed())
+            && ui.ctx().pointer_latest_pos().is_some_and(|pos| {
+                ui.max_rect().contains(pos)
+                    && ui.ctx().layer_id_at(pos) == Some(egui::LayerId::background())
+            });
+        if pressed_on_canvas {
+            for widget in self.functions.values_mut() {
+                widget.is_selected = false;
+            }
+            self.last_selected = None;
+        }
+    }
+
+    fn render_debug_controls(&mut self, ui: &mut egui::Ui) {
+        if !self.debugger.is_active() {
+            let debug_btn =
+                ui.add_enabled(!self.is_cyclic, egui::Button::new("🐞 Debug").rounding(5.0));
+            if debug_btn.clicked() {
+                self.execute(Command::StartDebugging);
+            }
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("⏭").on_hover_text("Step").clicked() {
+                self.execute(Command::DebugStep);
+            }
+            if ui.button("⏩").on_hover_text("Continue").clicked() {
+                self.execute(Command::DebugContinue);
+            }
+            if ui.button("⏹").on_hover_text("Stop").clicked() {
+                self.execute(Command::StopDebugging);
+            }
+        });
+    }
+
+    /// Function ids in the order they should run, or `None` if links form a cycle.
+    /// Functions without links are run after the linked ones.
+    fn execution_order(&self) -> Option<Vec<u16>> {
+        graph::execution_order(
+            self.functions.keys().copied(),
+            self.scheduling_links()
+                .map(|link| (link.start.function_id, link.end.function_id)),
+        )
+    }
+
+    /// The project without editor state, as the JS API and exports see it.
+    fn graph(&self) -> Graph {
+        Graph {
+            functions: self
+                .functions
+                .iter()
+                .map(|(id, widget)| (*id, widget.runnable.clone()))
+                .collect(),
+            links: self
+                .links
+                .iter()
+                .filter(|link| !link.should_be_deleted)
+                .map(|link| GraphLink {
+                    start: link.start.clone(),
+                    end: link.end.clone(),
+                })
+                .collect(),
+            tests: self.tests.cases.clone(),
+            layout: Layout::default(),
+        }
+    }
+
+    /// The graph with where everything is on the canvas, for "Copy project JSON".
+    fn project(&self) -> Graph {
+        let mut layout = Layout {
+            positions: self
+                .functions
+                .iter()
+                .map(|(id, widget)| (*id, widget.position))
+                .collect(),
+            reroutes: self
+                .links
+                .iter()
+                .filter(|link| !link.should_be_deleted && !link.reroutes.is_empty())
+                .map(|link| Reroute {
+                    start: link.start.clone(),
+                    end: link.end.clone(),
+                    points: link.reroutes.clone(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        self.annotations.write_layout(&mut layout);
+        Graph {
+            layout,
+            ..self.graph()
+        }
+    }
+
+    /// Where the nodes and their ports are on the canvas, for the SVG export.
+    fn placements(&self) -> HashMap<u16, Placement> {
+        self.functions
+            .iter()
+            .map(|(id, widget)| {
+                let rect = Rect::from_min_size(widget.position, widget.layout_size());
+                let placement = if widget.is_collapsed || widget.mode == WidgetMode::Code {
+                    Placement::title_only(&widget.runnable, rect)
+                } else {
+                    Placement {
+                        rect,
+                        ports: widget
+                            .runnable
+                            .inputs
+                            .iter()
+                            .chain(widget.runnable.outputs.iter())
+                            .map(|(param_id, param)| (*param_id, param.pos))
+                            .collect(),
+                        show_ports: true,
+                    }
+                };
+                (*id, placement)
+            })
+            .collect()
+    }
+
+    /// Runs the function the debugger is paused on. Its outputs reach the
+    /// linked inputs when the executor hands the run back.
+    fn debug_step(&mut self) {
+        self.debug_run(false);
+    }
+
+    /// Runs until the next function with a breakpoint or the end of the graph.
+    fn debug_continue(&mut self) {
+        self.debug_run(true);
+    }
+
+    fn debug_run(&mut self, until_breakpoint: bool) {
+        if self.executor.is_running() {
+            return;
+        }
+        let mut ids = Vec::new();
+        while let Some(function_id) = self.debugger.current() {
+            let has_breakpoint = self
+                .functions
+                .get(&function_id)
+                .is_some_and(|widget| widget.has_breakpoint);
+            if !ids.is_empty() && (!until_breakpoint || has_breakpoint) {
+                break;
+            }
+            self.debugger.advance();
+            if self.functions.contains_key(&function_id) {
+                ids.push(function_id);
+            }
+        }
+        if ids.is_empty() {
+            return;
+        }
+        // The pass is over once the debugger ran out of functions.
+        let latch_delays = !self.debugger.is_active();
+        self.executor
+            .start(self.graph(), 1, RunScope::Functions { ids, latch_delays });
+    }
+
+    fn is_delay(&self, function_id: u16) -> bool {
+        self.functions
+            .get(&function_id)
+            .is_some_and(|widget| widget.is_delay())
+    }
+
+    /// Links that order the execution. Links into a delay are left out, as the
+    /// delay only uses their value on the next iteration.
+    fn scheduling_links(&self) -> impl Iterator<Item = &Link> {
+        self.links
+            .iter()
+            .filter(|link| !link.should_be_deleted && !self.is_delay(link.end.function_id))
+    }
+
     fn check_for_cycles(&mut self) {
-        let g: StableGraph<(), (), Directed, u32> = StableGraph::from_edges(self.links.iter());
+        let g: StableGraph<(), (), Directed, u32> =
+            StableGraph::from_edges(self.scheduling_links());
 
         self.is_cyclic = is_cyclic_directed(&g);
+
+        self.diagnostics.cycles = if self.is_cyclic {
+            tarjan_scc(&g)
+                .into_iter()
+                .filter(|component| {
+                    component.len() > 1 || g.contains_edge(component[0], component[0])
+                })
+                .map(|component| {
+                    component
+                        .into_iter()
+                        .map(|node| node.index() as u16)
+                        .collect()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.diagnostics.cycle_links = self
+            .scheduling_links()
+            .filter(|link| self.diagnostics.is_in_cycle(&link.start, &link.end))
+            .map(|link| (link.start.clone(), link.end.clone()))
+            .collect();
+    }
+
+    /// Whether `to` can be reached from `from` following the links.
+    fn has_path(&self, from: u16, to: u16) -> bool {
+        let mut visited = vec![from];
+        let mut queue = vec![from];
+        while let Some(function_id) = queue.pop() {
+            if function_id == to {
+                return true;
+            }
+            for link in self.scheduling_links() {
+                if link.start.function_id == function_id && !visited.contains(&link.end.function_id)
+                {
+                    visited.push(link.end.function_id);
+                    queue.push(link.end.function_id);
+                }
+            }
+        }
+        false
     }
 
     fn update_last_values(&mut self) {
@@ -529,29 +1697,154 @@ impl TemplateApp {
                 .last_value
                 .clone();
 
-            if start_point_param.is_some() {
-                if let Some(last_value) = start_point_param {
-                    let end_point_param = self
-                        .functions
-                        .get_mut(&current_link.end.function_id)
-                        .unwrap()
-                        .runnable
-                        .inputs
-                        .get_mut(&current_link.end.param_id)
-                        .unwrap();
-
-                    end_point_param.last_value = Some(last_value);
+            if let Some(last_value) = start_point_param {
+                let changed_widget = self
+                    .functions
+                    .get_mut(&current_link.end.function_id)
+                    .unwrap();
+                let end_point_param = changed_widget
+                    .runnable
+                    .inputs
+                    .get_mut(&current_link.end.param_id)
+                    .unwrap();
 
-                    let changed_widget = self
-                        .functions
-                        .get_mut(&current_link.end.function_id)
-                        .unwrap();
+                let value_changed = end_point_param.update_last_value(last_value);
+                if value_changed {
+                    current_link.propagated_at = Some(trace::now());
+                }
 
-                    changed_widget.runnable.run(&changed_widget.engine)
+                // Delays only advance when the whole graph is stepped.
+                if (value_changed || changed_widget.is_dirty) && !changed_widget.is_delay() {
+                    self.pending_runs.insert(current_link.end.function_id);
                 }
             }
         }
     }
+
+    fn take_run_requests(&mut self) {
+        for (function_id, widget) in self.functions.iter_mut() {
+            if std::mem::take(&mut widget.run_request) {
+                self.pending_runs.insert(*function_id);
+            }
+        }
+    }
+
+    fn take_inspect_requests(&mut self) {
+        for (function_id, widget) in self.functions.iter_mut() {
+            if let Some(param_id) = widget.inspect_request.take() {
+                self.inspector.inspect(LinkVertex {
+                    function_id: *function_id,
+                    param_id,
+                });
+            }
+        }
+    }
+
+    /// Moves trace entries of every run made during this frame into the trace log.
+    fn collect_trace(&mut self) {
+        let entries: Vec<TraceEntry> = self
+            .functions
+            .values_mut()
+            .flat_map(|widget| widget.pending_trace.drain(..))
+            .collect();
+        for entry in entries.iter() {
+            if let Some(message) = &entry.error {
+                self.events.push(GraphEvent::NodeError {
+                    id: entry.function_id,
+                    name: entry.function_name.clone(),
+                    message: message.clone(),
+                });
+            }
+        }
+        self.trace.record_run(entries);
+    }
+
+    /// Names, links and a fingerprint of everything saved, for the event tracker.
+    fn graph_state(&self) -> GraphState {
+        let nodes = self
+            .functions
+            .iter()
+            .map(|(id, widget)| (*id, widget.runnable.name.clone()))
+            .collect();
+        let links = self
+            .links
+            .iter()
+            .filter(|link| !link.should_be_deleted)
+            .map(|link| (link.start.clone(), link.end.clone()))
+            .collect();
+        let fingerprint = events::fingerprint((
+            self.functions
+                .values()
+                .map(|widget| {
+                    let params: Vec<(u16, &str)> = widget
+                        .runnable
+                        .inputs
+                        .iter()
+                        .chain(widget.runnable.outputs.iter())
+                        .map(|(id, param)| (*id, param.param_name.as_str()))
+                        .collect();
+                    (
+                        widget.id,
+                        &widget.runnable.name,
+                        &widget.runnable.code,
+                        params,
+                        widget.position.x as i32,
+                        widget.position.y as i32,
+                    )
+                })
+                .collect::<Vec<_>>(),
+            self.links
+                .iter()
+                .filter(|link| !link.should_be_deleted)
+                .map(|link| {
+                    (
+                        link.start.function_id,
+                        link.start.param_id,
+                        link.end.function_id,
+                        link.end.param_id,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ));
+        GraphState {
+            nodes,
+            links,
+            fingerprint,
+        }
+    }
+}
+
+/// Where a reroute knot can be grabbed.
+fn knot_rect(knot: Pos2) -> Rect {
+    Rect::from_center_size(knot, Vec2::splat(KNOT_RADIUS * 3.0))
+}
+
+/// Draws the reroute knots of a link. Knots can be dragged and are removed by a double-click.
+fn render_knots(ui: &mut egui::Ui, link_index: usize, knots: &mut Vec<Pos2>, stroke: egui::Stroke) {
+    let mut removed = None;
+    for (knot_index, knot) in knots.iter_mut().enumerate() {
+        let response = ui.interact(
+            knot_rect(*knot),
+            egui::Id::new(("link_knot", link_index, knot_index)),
+            Sense::click_and_drag(),
+        );
+        if response.dragged() {
+            *knot += response.drag_delta();
+        }
+        if response.double_clicked() {
+            removed = Some(knot_index);
+        }
+        let radius = if response.hovered() {
+            KNOT_RADIUS * 1.5
+        } else {
+            KNOT_RADIUS
+        };
+        ui.painter()
+            .circle(*knot, radius, ui.visuals().window_fill, stroke);
+    }
+    if let Some(knot_index) = removed {
+        knots.remove(knot_index);
+    }
 }
 
 impl eframe::App for TemplateApp {
@@ -561,13 +1854,27 @@ impl eframe::App for TemplateApp {
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         // Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
+        self.poll_executor(ctx);
+        self.tests.poll(ctx);
+        self.handle_shortcuts(ctx);
+        self.show_palette(ctx);
+        self.import_scripts(ctx);
         self.render_side_panel(ctx);
 
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, powered_by_egui_and_eframe);
+        egui::TopBottomPanel::bottom("trace_panel")
+            .resizable(true)
+            .default_height(150.0)
+            .show(ctx, |ui| self.trace.ui(ui));
+        self.inspector.show(ctx, &self.functions);
+        if self.tests.is_open {
+            let graph = self.graph();
+            self.tests.show(ctx, &graph);
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             self.check_for_cycles();
@@ -575,19 +1882,71 @@ impl eframe::App for TemplateApp {
             self.delete_old_links();
 
             let stroke = ui.visuals().widgets.hovered.bg_stroke;
+            self.canvas_rect = ui.max_rect();
+            self.grid.paint(ui, self.canvas_rect);
+            let node_rects: Vec<(u16, Rect)> = self
+                .functions
+                .iter()
+                .map(|(id, widget)| {
+                    (
+                        *id,
+                        Rect::from_min_size(widget.position, widget.layout_size()),
+                    )
+                })
+                .collect();
+            for (id, delta) in self.annotations.show(ui, &node_rects) {
+                if let Some(widget) = self.functions.get_mut(&id) {
+                    let position = widget.position + delta;
+                    widget.move_to(position);
+                }
+            }
 
-            for (_, ele) in self.functions.iter_mut() {
+            let current_function = self.debugger.current();
+            let widget_shortcuts = self.keymap.widget_shortcuts();
+            for (id, ele) in self.functions.iter_mut() {
+                ele.is_current = current_function == Some(*id);
+                ele.port_hit_size = self.port_hit_size;
+                ele.shortcuts = widget_shortcuts;
                 ui.add(ele);
             }
 
-            self.cancel_link_if_esc(ui);
+            self.update_selection(ui);
+            self.snap_dragged_widgets(ui);
+            self.begin_link_drag();
             self.create_unfinished_link_if_clicked(ui, stroke);
             self.create_finished_links();
+            self.finish_link_drag(ui);
             self.render_links(ui, stroke);
 
             self.delete_old_links();
-            self.update_last_values();
+            if !self.debugger.is_active() {
+                self.update_last_values();
+            }
         });
+
+        self.handle_edits_during_run();
+        self.take_run_requests();
+        self.start_pending_runs();
+        self.collect_trace();
+        self.take_inspect_requests();
+        debugger::show_inspector(ctx, &self.debugger, &self.functions);
+        self.diagnostics.show(ctx, &self.functions);
+        self.keymap.show(ctx);
+
+        self.events.observe(self.graph_state());
+        events::dispatch(self.events.take());
+
+        if let Some(text) = self.clipboard.take() {
+            ctx.copy_text(text);
+        }
+        if std::mem::take(&mut self.should_save) {
+            if let Some(storage) = frame.storage_mut() {
+                self.save(storage);
+                storage.flush();
+                // Only explicit saves count, not the periodic autosave.
+                self.events.mark_saved();
+            }
+        }
     }
 }
 
@@ -600,3 +1959,30 @@ fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
         ui.label(".")
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runnable::Runnable;
+
+    #[test]
+    fn restoring_a_state_ending_in_an_imported_function_keeps_counting() {
+        let mut app = TemplateApp::default();
+        let runnable = Runnable::with_signature(
+            "greet".to_owned(),
+            r#"#{ Text: "hi " + Name }"#.to_owned(),
+            &["Name"],
+            &["Text"],
+        );
+        let widget = FunctionWidget::from_runnable(runnable, Pos2::ZERO);
+        app.functions.insert(widget.id, widget);
+
+        let json = serde_json::to_string(&app).unwrap();
+        let restored = serde_json::from_str::<TemplateApp>(&json)
+            .unwrap()
+            .restored();
+
+        assert_eq!(restored.functions.last().unwrap().1.runnable.name, "greet");
+        assert_eq!(restored.last_rect_id, 4);
+    }
+}
@@ -0,0 +1,166 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! Runs projects exported from the editor without opening it.
+
+use std::process::ExitCode;
+
+use rust_scripter::embed::Project;
+
+const USAGE: &str = "\
+Usage:
+    rust_scripter_cli run <project.json> [--input Function.Param=VALUE]... [--iterations N]
+    rust_scripter_cli inputs <project.json>
+    rust_scripter_cli script <project.json>
+    rust_scripter_cli import <script.rhai>
+    rust_scripter_cli diagram <project.json> [--format dot|mermaid|svg]
+    rust_scripter_cli test <project.json>
+
+VALUE is read as JSON, or taken as a string when it isn't valid JSON.
+`run` prints the graph outputs as a JSON object, `script` the project as a rhai script
+and `import` the project a rhai script makes. `diagram` prints a picture of the graph,
+DOT unless another format is given. `test` runs the test cases saved with the project
+and fails when one of them does.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("run") => run(&args[1..]),
+        Some("inputs") => inputs(&args[1..]),
+        Some("script") => script(&args[1..]),
+        Some("import") => import(&args[1..]),
+        Some("diagram") => diagram(&args[1..]),
+        Some("test") => test(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(USAGE.to_owned()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(args: &[String]) -> Result<Project, String> {
+    let path = args.first().ok_or(USAGE)?;
+    Project::load(path).map_err(|err| format!("{path}: {err}"))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut project = load(args)?;
+    let mut iterations = 1;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| format!("Missing value for {option}"))?;
+        match option.as_str() {
+            "--input" => {
+                let (key, value) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected Function.Param=VALUE, got {value}"))?;
+                let value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| serde_json::Value::String(value.to_owned()));
+                project
+                    .set_input_json(key, value)
+                    .map_err(|err| err.to_string())?;
+            }
+            "--iterations" => {
+                iterations = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of iterations: {value}"))?;
+            }
+            _ => return Err(format!("Unknown option {option}\n\n{USAGE}")),
+        }
+    }
+
+    project
+        .run_iterations(iterations)
+        .map_err(|err| err.to_string())?;
+    let outputs = project.outputs_json().map_err(|err| err.to_string())?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&outputs).unwrap_or_default()
+    );
+    Ok(())
+}
+
+fn inputs(args: &[String]) -> Result<(), String> {
+    let project = load(args)?;
+    for port in project.inputs() {
+        match port.value {
+            Some(value) => println!("{} = {value}", port.key),
+            None => println!("{}", port.key),
+        }
+    }
+    Ok(())
+}
+
+fn script(args: &[String]) -> Result<(), String> {
+    let script = load(args)?.to_script().map_err(|err| err.to_string())?;
+    print!("{script}");
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or(USAGE)?;
+    let source = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let project = Project::from_script(&source).map_err(|err| format!("{path}: {err}"))?;
+    for warning in project.warnings() {
+        eprintln!("{path}: {warning}");
+    }
+    println!("{}", project.to_json());
+    Ok(())
+}
+
+fn diagram(args: &[String]) -> Result<(), String> {
+    let project = load(args)?;
+    let format = match &args[1..] {
+        [] => "dot",
+        [option, format] if option == "--format" => format.as_str(),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let diagram = match format {
+        "dot" => project.to_dot(),
+        "mermaid" => project.to_mermaid(),
+        "svg" => project.to_svg(),
+        _ => {
+            return Err(format!(
+                "Unknown format {format}, expected dot, mermaid or svg"
+            ))
+        }
+    };
+    print!("{diagram}");
+    Ok(())
+}
+
+fn test(args: &[String]) -> Result<(), String> {
+    let project = load(args)?;
+    let results = project.run_tests();
+    for result in results.iter() {
+        if result.passed() {
+            println!("ok      {}", result.name);
+            continue;
+        }
+        println!("FAILED  {}", result.name);
+        if let Some(ref error) = result.error {
+            for line in error.lines() {
+                println!("        {line}");
+            }
+        }
+        for mismatch in result.mismatches.iter() {
+            println!("        {mismatch}");
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    println!("\n{} passed, {failed} failed", results.len() - failed);
+    if failed > 0 {
+        return Err(format!("{failed} of {} test cases failed", results.len()));
+    }
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use egui::{
+    Align2, Area, Color32, CursorIcon, Frame, Id, Key, Order, Pos2, Rect, Rounding, Sense, Stroke,
+    TextEdit, TextStyle, Ui, Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{Comment, Layout, Note};
+
+const TITLE_HEIGHT: f32 = 24.0;
+const RESIZE_HANDLE_SIZE: f32 = 12.0;
+const MIN_COMMENT_SIZE: Vec2 = Vec2 { x: 120.0, y: 60.0 };
+const COMMENT_PADDING: f32 = 20.0;
+
+const PALETTE: [(&str, Color32); 6] = [
+    ("Gray", Color32::from_rgb(140, 140, 140)),
+    ("Blue", Color32::from_rgb(70, 130, 200)),
+    ("Green", Color32::from_rgb(80, 160, 90)),
+    ("Red", Color32::from_rgb(200, 70, 70)),
+    ("Purple", Color32::from_rgb(150, 90, 190)),
+    ("Yellow", Color32::from_rgb(210, 180, 60)),
+];
+
+/// A titled frame drawn behind nodes. Dragging its title moves the nodes inside.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CommentBox {
+    pub id: u16,
+    pub title: String,
+    pub rect: Rect,
+    pub color: Color32,
+    #[serde(skip)]
+    is_renaming: bool,
+    /// Nodes picked up when the drag started.
+    #[serde(skip)]
+    members: Vec<u16>,
+    #[serde(skip)]
+    should_be_deleted: bool,
+}
+
+/// Free text pinned anywhere on the canvas.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StickyNote {
+    pub id: u16,
+    pub text: String,
+    pub pos: Pos2,
+    #[serde(skip)]
+    should_be_deleted: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Annotations {
+    pub comments: Vec<CommentBox>,
+    pub notes: Vec<StickyNote>,
+}
+
+impl Annotations {
+    /// Adds a comment around `content`, grown by a margin and a title bar.
+    pub fn add_comment(&mut self, content: Rect) {
+        let rect = Rect::from_min_max(
+            content.min - Vec2::new(COMMENT_PADDING, COMMENT_PADDING + TITLE_HEIGHT),
+            content.max + Vec2::splat(COMMENT_PADDING),
+        );
+        self.comments.push(CommentBox {
+            id: fastrand::u16(..),
+            title: "Comment".to_owned(),
+            rect: Rect::from_min_size(rect.min, rect.size().max(MIN_COMMENT_SIZE)),
+            color: PALETTE[0].1,
+            is_renaming: false,
+            members: Vec::new(),
+            should_be_deleted: false,
+        });
+    }
+
+    pub fn add_note(&mut self, pos: Pos2) {
+        self.notes.push(StickyNote {
+            id: fastrand::u16(..),
+            text: "Note".to_owned(),
+            pos,
+            should_be_deleted: false,
+        });
+    }
+
+    /// Writes the comments and notes into the layout saved with the project.
+    pub fn write_layout(&self, layout: &mut Layout) {
+        layout.comments = self
+            .comments
+            .iter()
+            .map(|comment| Comment {
+                title: comment.title.clone(),
+                rect: comment.rect,
+                color: comment.color.to_array(),
+            })
+            .collect();
+        layout.notes = self
+            .notes
+            .iter()
+            .map(|note| Note {
+                text: note.text.clone(),
+                pos: note.pos,
+            })
+            .collect();
+    }
+
+    /// Adds the comments and notes of a saved layout, moved by `offset`.
+    pub fn add_from_layout(&mut self, layout: &Layout, offset: Vec2) {
+        for comment in layout.comments.iter() {
+            let [r, g, b, a] = comment.color;
+            self.comments.push(CommentBox {
+                id: fastrand::u16(..),
+                title: comment.title.clone(),
+                rect: comment.rect.translate(offset),
+                color: Color32::from_rgba_premultiplied(r, g, b, a),
+                is_renaming: false,
+                members: Vec::new(),
+                should_be_deleted: false,
+            });
+        }
+        for note in layout.notes.iter() {
+            self.notes.push(StickyNote {
+                id: fastrand::u16(..),
+                text: note.text.clone(),
+                pos: note.pos + offset,
+                should_be_deleted: false,
+            });
+        }
+    }
+
+    /// Moves every comment and note, used when the canvas pans.
+    pub fn translate(&mut self, delta: Vec2) {
+        for comment in self.comments.iter_mut() {
+            comment.rect = comment.rect.translate(delta);
+        }
+        for note in self.notes.iter_mut() {
+            note.pos += delta;
+        }
+    }
+
+    /// Draws comments on the canvas background and notes above it. Returns how
+    /// far each node inside a dragged comment has to move.
+    pub fn show(&mut self, ui: &mut Ui, node_rects: &[(u16, Rect)]) -> Vec<(u16, Vec2)> {
+        let mut moves = Vec::new();
+        for comment in self.comments.iter_mut() {
+            moves.extend(comment.show(ui, node_rects));
+        }
+        self.comments.retain(|comment| !comment.should_be_deleted);
+
+        for note in self.notes.iter_mut() {
+            note.show(ui);
+        }
+        self.notes.retain(|note| !note.should_be_deleted);
+
+        moves
+    }
+}
+
+impl CommentBox {
+    fn show(&mut self, ui: &mut Ui, node_rects: &[(u16, Rect)]) -> Vec<(u16, Vec2)> {
+        let id = Id::new(("comment", self.id));
+        let title_rect =
+            Rect::from_min_size(self.rect.min, Vec2::new(self.rect.width(), TITLE_HEIGHT));
+        let handle_rect = Rect::from_min_size(
+            self.rect.max - Vec2::splat(RESIZE_HANDLE_SIZE),
+            Vec2::splat(RESIZE_HANDLE_SIZE),
+        );
+
+        let painter = ui.painter();
+        painter.rect(
+            self.rect,
+            Rounding::same(4.0),
+            self.color.gamma_multiply(0.15),
+            Stroke::new(1.5_f32, self.color),
+        );
+        painter.rect_filled(
+            title_rect,
+            Rounding {
+                nw: 4.0,
+                ne: 4.0,
+                sw: 0.0,
+                se: 0.0,
+            },
+            self.color.gamma_multiply(0.6),
+        );
+
+        let mut moves = Vec::new();
+        let title_response = ui.interact(title_rect, id.with("title"), Sense::click_and_drag());
+        if title_response.drag_started() {
+            self.members = node_rects
+                .iter()
+                .filter(|(_, rect)| self.rect.contains_rect(*rect))
+                .map(|(node_id, _)| *node_id)
+                .collect();
+        }
+        if title_response.dragged() {
+            let delta = title_response.drag_delta();
+            self.rect = self.rect.translate(delta);
+            moves.extend(self.members.iter().map(|node_id| (*node_id, delta)));
+        }
+        if title_response.double_clicked() {
+            self.is_renaming = true;
+        }
+        title_response.context_menu(|ui| {
+            if ui.button("Rename").clicked() {
+                self.is_renaming = true;
+                ui.close_menu();
+            }
+            ui.menu_button("Color", |ui| {
+                for (name, color) in PALETTE {
+                    if ui.button(name).clicked() {
+                        self.color = color;
+                        ui.close_menu();
+                    }
+                }
+            });
+            if ui.button("Delete").clicked() {
+                self.should_be_deleted = true;
+                ui.close_menu();
+            }
+        });
+
+        if self.is_renaming {
+            let edit_rect = title_rect.shrink2(Vec2::new(4.0, 2.0));
+            let edit_response = ui.put(edit_rect, TextEdit::singleline(&mut self.title));
+            edit_response.request_focus();
+            if edit_response.lost_focus() || ui.input(|i| i.key_pressed(Key::Enter)) {
+                self.is_renaming = false;
+            }
+        } else {
+            ui.painter().text(
+                title_rect.left_center() + Vec2::new(6.0, 0.0),
+                Align2::LEFT_CENTER,
+                &self.title,
+                TextStyle::Button.resolve(ui.style()),
+                ui.visuals().strong_text_color(),
+            );
+        }
+
+        let handle_response = ui.interact(handle_rect, id.with("resize"), Sense::drag());
+        if handle_response.hovered() || handle_response.dragged() {
+            ui.ctx().set_cursor_icon(CursorIcon::ResizeNwSe);
+        }
+        if handle_response.dragged() {
+            self.rect.max += handle_response.drag_delta();
+            self.rect.max = self.rect.max.max(self.rect.min + MIN_COMMENT_SIZE);
+        }
+        ui.painter().line_segment(
+            [handle_rect.right_top(), handle_rect.left_bottom()],
+            Stroke::new(1.0_f32, self.color),
+        );
+
+        moves
+    }
+}
+
+impl StickyNote {
+    fn show(&mut self, ui: &Ui) {
+        let area_response = Area::new(Id::new(("sticky_note", self.id)))
+            .order(Order::Background)
+            .current_pos(self.pos)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style())
+                    .fill(Color32::from_rgb(250, 235, 150))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                TextEdit::multiline(&mut self.text)
+                                    .frame(false)
+                                    .desired_rows(2)
+                                    .desired_width(140.0)
+                                    .text_color(Color32::BLACK),
+                            );
+                            if ui.small_button("❌").clicked() {
+                                self.should_be_deleted = true;
+                            }
+                        });
+                    });
+            });
+        self.pos += area_response.response.drag_delta();
+    }
+}
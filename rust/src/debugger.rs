@@ -0,0 +1,120 @@
+use egui::{Color32, Grid, RichText, ScrollArea, Window};
+use indexmap::IndexMap;
+
+use crate::function_widget::FunctionWidget;
+
+/// Step-through execution of the graph: functions are run one at a time
+/// in toposort order, pausing on functions with a breakpoint.
+#[derive(Default, Debug)]
+pub struct Debugger {
+    order: Vec<u16>,
+    position: usize,
+    is_active: bool,
+}
+
+impl Debugger {
+    pub fn start(&mut self, order: Vec<u16>) {
+        self.order = order;
+        self.position = 0;
+        self.is_active = !self.order.is_empty();
+    }
+
+    pub fn stop(&mut self) {
+        self.order.clear();
+        self.position = 0;
+        self.is_active = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    /// The function that will be run on the next step.
+    pub fn current(&self) -> Option<u16> {
+        if self.is_active {
+            self.order.get(self.position).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Moves past the current function, finishing the session after the last one.
+    pub fn advance(&mut self) {
+        self.position += 1;
+        if self.position >= self.order.len() {
+            self.stop();
+        }
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.order.len())
+    }
+}
+
+/// Shows the current debugging position and every parameter value of the graph.
+pub fn show_inspector(
+    ctx: &egui::Context,
+    debugger: &Debugger,
+    functions: &IndexMap<u16, FunctionWidget>,
+) {
+    if !debugger.is_active() {
+        return;
+    }
+
+    Window::new("Debugger")
+        .default_pos([160.0, 400.0])
+        .resizable(true)
+        .show(ctx, |ui| {
+            let (position, total) = debugger.progress();
+            let current_name = debugger
+                .current()
+                .and_then(|id| functions.get(&id))
+                .map_or("-", |widget| widget.runnable.name.as_str());
+            ui.label(format!(
+                "Next: {} ({}/{})",
+                current_name,
+                position + 1,
+                total
+            ));
+            ui.separator();
+
+            ScrollArea::vertical().show(ui, |ui| {
+                for (id, widget) in functions.iter() {
+                    let mut title = RichText::new(&widget.runnable.name).strong();
+                    if debugger.current() == Some(*id) {
+                        title = title.color(Color32::YELLOW);
+                    }
+                    ui.collapsing(title, |ui| {
+                        Grid::new(("debugger_values", *id))
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                let params = widget
+                                    .runnable
+                                    .inputs
+                                    .values()
+                                    .map(|param| ("in", param))
+                                    .chain(
+                                        widget
+                                            .runnable
+                                            .outputs
+                                            .values()
+                                            .map(|param| ("out", param)),
+                                    );
+                                for (direction, param) in params {
+                                    ui.label(direction);
+                                    ui.label(&param.param_name);
+                                    ui.label(
+                                        param
+                                            .last_value
+                                            .as_ref()
+                                            .map_or("<none>".to_owned(), |value| value.to_string()),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                }
+            });
+        });
+}
@@ -0,0 +1,93 @@
+use egui::{Color32, RichText, Window};
+use indexmap::IndexMap;
+
+use crate::function_widget::FunctionWidget;
+use crate::runnable::LinkVertex;
+
+pub const CYCLE_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
+
+/// Problems found in the graph, shown in a window while there are any.
+#[derive(Default, Debug)]
+pub struct Diagnostics {
+    /// Function ids of every strongly connected component that forms a cycle.
+    pub cycles: Vec<Vec<u16>>,
+    /// Links whose both ends lie in the same cycle.
+    pub cycle_links: Vec<(LinkVertex, LinkVertex)>,
+    /// Why the last attempt to link parameters was refused.
+    pub message: Option<String>,
+}
+
+impl Diagnostics {
+    pub fn is_in_cycle(&self, start: &LinkVertex, end: &LinkVertex) -> bool {
+        self.cycles
+            .iter()
+            .any(|cycle| cycle.contains(&start.function_id) && cycle.contains(&end.function_id))
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, functions: &IndexMap<u16, FunctionWidget>) {
+        if self.cycles.is_empty() && self.message.is_none() {
+            return;
+        }
+
+        Window::new("Diagnostics")
+            .default_pos([160.0, 200.0])
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(ref message) = self.message {
+                    let mut dismissed = false;
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(format!("⚠ {message}")).color(Color32::YELLOW));
+                        dismissed = ui.small_button("❌").clicked();
+                    });
+                    if dismissed {
+                        self.message = None;
+                    }
+                }
+
+                for (index, cycle) in self.cycles.iter().enumerate() {
+                    ui.label(
+                        RichText::new(format!("Cycle #{}: {}", index + 1, names(functions, cycle)))
+                            .color(CYCLE_COLOR),
+                    );
+                    for (start, end) in self.cycle_links.iter() {
+                        if cycle.contains(&start.function_id) && cycle.contains(&end.function_id) {
+                            ui.label(format!(
+                                "    {} → {}",
+                                param_name(functions, start),
+                                param_name(functions, end)
+                            ));
+                        }
+                    }
+                }
+                if !self.cycles.is_empty() {
+                    ui.label("Running is disabled until the cycles are broken.");
+                }
+            });
+    }
+}
+
+fn names(functions: &IndexMap<u16, FunctionWidget>, ids: &[u16]) -> String {
+    ids.iter()
+        .map(|id| {
+            functions
+                .get(id)
+                .map_or(format!("#{id}"), |widget| widget.runnable.name.clone())
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn param_name(functions: &IndexMap<u16, FunctionWidget>, vertex: &LinkVertex) -> String {
+    let Some(widget) = functions.get(&vertex.function_id) else {
+        return format!("#{}", vertex.function_id);
+    };
+    let param = widget
+        .runnable
+        .inputs
+        .get(&vertex.param_id)
+        .or_else(|| widget.runnable.outputs.get(&vertex.param_id));
+    match param {
+        Some(param) => format!("{}.{}", widget.runnable.name, param.param_name),
+        None => format!("{}.#{}", widget.runnable.name, vertex.param_id),
+    }
+}
@@ -0,0 +1,345 @@
+//! Static pictures of a graph for design reviews: Graphviz DOT, a Mermaid
+//! flowchart and an SVG drawn like the canvas. All three show the function
+//! titles, their ports and the links between ports.
+
+use std::collections::HashMap;
+
+use emath::{Pos2, Rect, Vec2};
+
+use crate::graph::Graph;
+use crate::layout::layered_layout;
+use crate::runnable::Runnable;
+
+const NODE_WIDTH: f32 = 230.0;
+const DELAY_WIDTH: f32 = 160.0;
+const TITLE_HEIGHT: f32 = 24.0;
+const ROW_HEIGHT: f32 = 22.0;
+const PORT_INSET: f32 = 10.0;
+const PORT_RADIUS: f32 = 5.0;
+/// Where links attach to a node that doesn't show its ports.
+const TITLE_PORT_Y: f32 = 16.0;
+/// How far a link goes straight out of a port before it turns.
+const LINK_HANDLE: f32 = 30.0;
+/// Room around the nodes for the loops of backwards links.
+const MARGIN: f32 = 40.0;
+
+// The default dark theme of the editor.
+const BACKGROUND: &str = "#1b1b1b";
+const NODE_STROKE: &str = "#3c3c3c";
+const TITLE_COLOR: &str = "#dcdcdc";
+const LABEL_COLOR: &str = "#8c8c8c";
+const LINK_COLOR: &str = "#969696";
+const PORT_COLOR: &str = "#800000";
+
+/// Where a function is drawn: its window and the centre of each port by param id.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub rect: Rect,
+    pub ports: HashMap<u16, Pos2>,
+    /// Collapsed windows and code views hide their ports, links go to the title.
+    pub show_ports: bool,
+}
+
+impl Placement {
+    /// One row per port under the title, inputs on the left and outputs on the right.
+    pub fn rows(runnable: &Runnable, rect: Rect) -> Self {
+        let row = |index: usize| rect.top() + TITLE_HEIGHT + ROW_HEIGHT * (index as f32 + 0.5);
+        let inputs =
+            runnable.inputs.keys().enumerate().map(|(index, param_id)| {
+                (*param_id, Pos2::new(rect.left() + PORT_INSET, row(index)))
+            });
+        let outputs = runnable
+            .outputs
+            .keys()
+            .enumerate()
+            .map(|(index, param_id)| (*param_id, Pos2::new(rect.right() - PORT_INSET, row(index))));
+        Self {
+            rect,
+            ports: inputs.chain(outputs).collect(),
+            show_ports: true,
+        }
+    }
+
+    /// Every input on the left of the title, every output on its right.
+    #[cfg(feature = "editor")]
+    pub fn title_only(runnable: &Runnable, rect: Rect) -> Self {
+        let y = rect.top() + TITLE_PORT_Y;
+        let inputs = runnable
+            .inputs
+            .keys()
+            .map(|param_id| (*param_id, Pos2::new(rect.left(), y)));
+        let outputs = runnable
+            .outputs
+            .keys()
+            .map(|param_id| (*param_id, Pos2::new(rect.right(), y)));
+        Self {
+            rect,
+            ports: inputs.chain(outputs).collect(),
+            show_ports: false,
+        }
+    }
+}
+
+/// Placements for a graph that isn't on a canvas, laid out in layers.
+pub fn layout(graph: &Graph) -> HashMap<u16, Placement> {
+    let nodes: Vec<(u16, Vec2)> = graph
+        .functions
+        .iter()
+        .map(|(function_id, runnable)| (*function_id, node_size(runnable)))
+        .collect();
+    let edges: Vec<(u16, u16)> = graph
+        .links
+        .iter()
+        .filter(|link| {
+            !graph
+                .functions
+                .get(&link.end.function_id)
+                .is_some_and(Runnable::is_delay)
+        })
+        .map(|link| (link.start.function_id, link.end.function_id))
+        .collect();
+    let sizes: HashMap<u16, Vec2> = nodes.iter().copied().collect();
+    layered_layout(&nodes, &edges, Pos2::ZERO)
+        .into_iter()
+        .map(|(function_id, position)| {
+            let rect = Rect::from_min_size(position, sizes[&function_id]);
+            let placement = Placement::rows(&graph.functions[&function_id], rect);
+            (function_id, placement)
+        })
+        .collect()
+}
+
+fn node_size(runnable: &Runnable) -> Vec2 {
+    let rows = runnable.inputs.len().max(runnable.outputs.len()).max(1);
+    Vec2::new(
+        if runnable.is_delay() {
+            DELAY_WIDTH
+        } else {
+            NODE_WIDTH
+        },
+        TITLE_HEIGHT + ROW_HEIGHT * rows as f32 + PORT_INSET,
+    )
+}
+
+/// A record per function with its inputs, title and outputs side by side.
+pub fn dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph project {\n    rankdir=LR;\n");
+    dot += "    node [shape=record, fontname=\"Helvetica\", fontsize=11];\n\n";
+    for (function_id, runnable) in graph.functions.iter() {
+        let ports = |params: Vec<String>| format!("{{{}}}", params.join("|"));
+        let mut fields = Vec::new();
+        if !runnable.inputs.is_empty() {
+            fields.push(ports(
+                runnable
+                    .inputs
+                    .iter()
+                    .map(|(param_id, param)| {
+                        format!("<i{param_id}> {}", dot_escape(&param.param_name))
+                    })
+                    .collect(),
+            ));
+        }
+        fields.push(dot_escape(&runnable.name));
+        if !runnable.outputs.is_empty() {
+            fields.push(ports(
+                runnable
+                    .outputs
+                    .iter()
+                    .map(|(param_id, param)| {
+                        format!("<o{param_id}> {}", dot_escape(&param.param_name))
+                    })
+                    .collect(),
+            ));
+        }
+        let style = if runnable.is_delay() {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        dot += &format!(
+            "    f{function_id} [label=\"{}\"{style}];\n",
+            fields.join("|")
+        );
+    }
+    dot += "\n";
+    for link in graph.links.iter() {
+        dot += &format!(
+            "    f{}:o{}:e -> f{}:i{}:w;\n",
+            link.start.function_id, link.start.param_id, link.end.function_id, link.end.param_id
+        );
+    }
+    dot += "}\n";
+    dot
+}
+
+/// Record labels treat these as field syntax.
+fn dot_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A subgraph per function holding a node per port, links between the ports.
+pub fn mermaid(graph: &Graph) -> String {
+    let mut mermaid = String::from("flowchart LR\n");
+    for (function_id, runnable) in graph.functions.iter() {
+        mermaid += &format!(
+            "    subgraph f{function_id} [\"{}\"]\n",
+            mermaid_escape(&runnable.name)
+        );
+        for (param_id, param) in runnable.inputs.iter().chain(runnable.outputs.iter()) {
+            mermaid += &format!(
+                "        f{function_id}_{param_id}([\"{}\"])\n",
+                mermaid_escape(&param.param_name)
+            );
+        }
+        mermaid += "    end\n";
+        if runnable.is_delay() {
+            mermaid += &format!("    style f{function_id} stroke-dasharray: 5 5\n");
+        }
+    }
+    for link in graph.links.iter() {
+        mermaid += &format!(
+            "    f{}_{} --> f{}_{}\n",
+            link.start.function_id, link.start.param_id, link.end.function_id, link.end.param_id
+        );
+    }
+    mermaid
+}
+
+/// Quotes end a label and `#` starts an entity, so both are written as entities.
+fn mermaid_escape(text: &str) -> String {
+    text.replace('#', "#35;").replace('"', "#quot;")
+}
+
+/// The functions at `placements` with bezier links between their ports,
+/// framed by a margin.
+pub fn svg(graph: &Graph, placements: &HashMap<u16, Placement>) -> String {
+    let bounds = placements
+        .values()
+        .map(|placement| placement.rect)
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(Rect::ZERO);
+    let offset = Vec2::splat(MARGIN) - bounds.min.to_vec2();
+    let size = bounds.size() + Vec2::splat(MARGIN * 2.0);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" \
+         viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+        w = size.x,
+        h = size.y
+    );
+    svg += &format!("  <rect width=\"100%\" height=\"100%\" fill=\"{BACKGROUND}\"/>\n");
+
+    svg += &format!("  <g fill=\"none\" stroke=\"{LINK_COLOR}\">\n");
+    for link in graph.links.iter() {
+        let port = |function_id: u16, param_id: u16| {
+            placements
+                .get(&function_id)
+                .and_then(|placement| placement.ports.get(&param_id))
+                .map(|pos| *pos + offset)
+        };
+        let (Some(from), Some(to)) = (
+            port(link.start.function_id, link.start.param_id),
+            port(link.end.function_id, link.end.param_id),
+        ) else {
+            continue;
+        };
+        // Backwards links loop out of the start and into the end.
+        let dx = to.x - from.x;
+        let handle = if dx > 0.0 {
+            (dx / 2.0).max(LINK_HANDLE)
+        } else {
+            LINK_HANDLE * 2.0
+        };
+        svg += &format!(
+            "    <path d=\"M {:.1} {:.1} C {:.1} {:.1}, {:.1} {:.1}, {:.1} {:.1}\"/>\n",
+            from.x,
+            from.y,
+            from.x + handle,
+            from.y,
+            to.x - handle,
+            to.y,
+            to.x,
+            to.y
+        );
+    }
+    svg += "  </g>\n";
+
+    for (function_id, runnable) in graph.functions.iter() {
+        let Some(placement) = placements.get(function_id) else {
+            continue;
+        };
+        let rect = placement.rect.translate(offset);
+        let dash = if runnable.is_delay() {
+            " stroke-dasharray=\"5 5\""
+        } else {
+            ""
+        };
+        svg += "  <g>\n";
+        svg += &format!(
+            "    <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"6\" \
+             fill=\"{BACKGROUND}\" stroke=\"{NODE_STROKE}\"{dash}/>\n",
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height()
+        );
+        svg += &format!(
+            "    <text x=\"{:.1}\" y=\"{:.1}\" fill=\"{TITLE_COLOR}\">{}</text>\n",
+            rect.left() + PORT_INSET,
+            rect.top() + TITLE_PORT_Y,
+            svg_escape(&runnable.name)
+        );
+        if placement.show_ports {
+            svg += &format!(
+                "    <line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"{NODE_STROKE}\"/>\n",
+                rect.left(),
+                rect.right(),
+                y = rect.top() + TITLE_HEIGHT
+            );
+            let params = runnable
+                .inputs
+                .iter()
+                .map(|param| (param, "start", PORT_INSET))
+                .chain(
+                    runnable
+                        .outputs
+                        .iter()
+                        .map(|param| (param, "end", -PORT_INSET)),
+                );
+            for ((param_id, param), anchor, label_offset) in params {
+                let Some(pos) = placement.ports.get(param_id).map(|pos| *pos + offset) else {
+                    continue;
+                };
+                svg += &format!(
+                    "    <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{PORT_RADIUS}\" fill=\"{PORT_COLOR}\" \
+                     stroke=\"{LINK_COLOR}\"/>\n",
+                    pos.x, pos.y
+                );
+                svg += &format!(
+                    "    <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{anchor}\" \
+                     dominant-baseline=\"middle\" fill=\"{LABEL_COLOR}\">{}</text>\n",
+                    pos.x + label_offset,
+                    pos.y,
+                    svg_escape(&param.param_name)
+                );
+            }
+        }
+        svg += "  </g>\n";
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,256 @@
+//! Running projects made in the editor from other crates, without the GUI.
+//! Build with only the `core` feature to leave eframe and egui out:
+//!
+//! ```no_run
+//! use rust_scripter::embed::Project;
+//!
+//! let mut project = Project::load("adder.json")?;
+//! project.set_input("Add #1.A", 2)?;
+//! project.set_input_json("Add #1.B", serde_json::json!(3))?;
+//! project.run()?;
+//! assert_eq!(project.outputs_json()?["Add #1.Sum"], 5);
+//! # Ok::<(), rust_scripter::embed::Error>(())
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use indexmap::IndexMap;
+use rhai::Engine;
+
+pub use crate::graph::Port;
+pub use crate::testing::{Mismatch, TestCase, TestResult};
+pub use rhai::Dynamic;
+
+use crate::diagram;
+use crate::graph::Graph;
+use crate::script;
+use crate::testing;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// The file isn't a project exported from the editor.
+    InvalidProject(serde_json::Error),
+    /// The script doesn't compile or defines no function.
+    InvalidScript(String),
+    /// No unlinked input has this `Function.Param` key.
+    UnknownInput(String),
+    /// A value can't be converted between JSON and rhai.
+    InvalidValue(String),
+    /// Links form a cycle that no delay breaks.
+    Cycle,
+    /// Functions whose code failed during a run. The others still ran.
+    Run(Vec<FunctionError>),
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionError {
+    pub function_id: u16,
+    pub function: String,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "Can't read the project: {err}"),
+            Error::InvalidProject(err) => write!(f, "Invalid project: {err}"),
+            Error::InvalidScript(message) => write!(f, "Invalid script: {message}"),
+            Error::UnknownInput(key) => write!(f, "No graph input called {key}"),
+            Error::InvalidValue(message) => write!(f, "Invalid value: {message}"),
+            Error::Cycle => write!(f, "Links form a cycle, break it with a delay"),
+            Error::Run(errors) => {
+                let messages: Vec<String> = errors
+                    .iter()
+                    .map(|err| format!("{}: {}", err.function, err.message))
+                    .collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::InvalidProject(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A project loaded from its JSON, with the engine to run it.
+pub struct Project {
+    graph: Graph,
+    engine: Engine,
+    /// What [`Project::from_script`] couldn't turn into links or values.
+    warnings: Vec<String>,
+}
+
+impl Project {
+    /// Reads a file saved with "Copy project JSON".
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(Self {
+            graph: serde_json::from_str(json).map_err(Error::InvalidProject)?,
+            engine: Engine::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// One function per `fn` of a rhai script, linked the way its main
+    /// section passes results around. See "Import script".
+    pub fn from_script(source: &str) -> Result<Self, Error> {
+        let script::Imported { graph, warnings } =
+            script::import(source).map_err(Error::InvalidScript)?;
+        Ok(Self {
+            graph,
+            engine: Engine::new(),
+            warnings,
+        })
+    }
+
+    /// Arguments and outputs of the script [`Project::from_script`] read that
+    /// didn't become links or values, one line each.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn to_json(&self) -> String {
+        self.graph.to_json()
+    }
+
+    /// The project as one standalone rhai script, see "Export as script".
+    pub fn to_script(&self) -> Result<String, Error> {
+        script::export(&self.graph).ok_or(Error::Cycle)
+    }
+
+    /// Graphviz DOT with a record per function, see "Export as DOT".
+    pub fn to_dot(&self) -> String {
+        diagram::dot(&self.graph)
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        diagram::mermaid(&self.graph)
+    }
+
+    /// An SVG of the functions laid out in layers like "Auto layout" does.
+    pub fn to_svg(&self) -> String {
+        diagram::svg(&self.graph, &diagram::layout(&self.graph))
+    }
+
+    /// Inputs nothing is linked to, the ones to bind before a run.
+    pub fn inputs(&self) -> Vec<Port> {
+        self.graph.inputs()
+    }
+
+    /// Outputs that aren't linked anywhere, the results of a run.
+    pub fn outputs(&self) -> Vec<Port> {
+        self.graph.outputs()
+    }
+
+    /// Binds the input called `key` (`Function.Param`).
+    pub fn set_input(&mut self, key: &str, value: impl Into<Dynamic>) -> Result<(), Error> {
+        self.graph
+            .set_input(key, value.into())
+            .map_err(|_| Error::UnknownInput(key.to_owned()))
+    }
+
+    pub fn set_input_json(&mut self, key: &str, value: serde_json::Value) -> Result<(), Error> {
+        let value =
+            rhai::serde::to_dynamic(value).map_err(|err| Error::InvalidValue(err.to_string()))?;
+        self.set_input(key, value)
+    }
+
+    /// Binds every input of an object keyed by `Function.Param`.
+    pub fn set_inputs_json(
+        &mut self,
+        values: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), Error> {
+        for (key, value) in values {
+            self.set_input_json(&key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Runs every function once, like "Run all" in the editor.
+    pub fn run(&mut self) -> Result<(), Error> {
+        self.run_iterations(1)
+    }
+
+    /// Runs the graph `iterations` times, like "Step" in the editor. Stops
+    /// after the first iteration with errors.
+    pub fn run_iterations(&mut self, iterations: u32) -> Result<(), Error> {
+        let order = self.graph.execution_order().ok_or(Error::Cycle)?;
+        for _ in 0..iterations {
+            let mut errors = Vec::new();
+            self.graph.step_with(&self.engine, &order, |entry| {
+                if let Some(message) = entry.error {
+                    errors.push(FunctionError {
+                        function_id: entry.function_id,
+                        function: entry.function_name,
+                        message,
+                    });
+                }
+            });
+            if !errors.is_empty() {
+                return Err(Error::Run(errors));
+            }
+        }
+        Ok(())
+    }
+
+    /// The test cases saved with the project.
+    pub fn tests(&self) -> &[TestCase] {
+        &self.graph.tests
+    }
+
+    /// Runs every test case on a copy of the graph, this project keeps its values.
+    pub fn run_tests(&self) -> Vec<TestResult> {
+        self.graph
+            .tests
+            .iter()
+            .map(|case| testing::run(&self.graph, &self.engine, case))
+            .collect()
+    }
+
+    /// Value of the output called `key` (`Function.Param`), `None` before the first run.
+    pub fn output(&self, key: &str) -> Option<Dynamic> {
+        self.graph
+            .outputs()
+            .into_iter()
+            .find(|port| port.key == key)
+            .and_then(|port| port.value)
+    }
+
+    pub fn output_values(&self) -> IndexMap<String, Option<Dynamic>> {
+        self.graph
+            .outputs()
+            .into_iter()
+            .map(|port| (port.key, port.value))
+            .collect()
+    }
+
+    /// Outputs as `{ "Function.Param": value }`, `null` before the first run.
+    pub fn outputs_json(&self) -> Result<serde_json::Map<String, serde_json::Value>, Error> {
+        self.output_values()
+            .into_iter()
+            .map(|(key, value)| {
+                let value = serde_json::to_value(value)
+                    .map_err(|err| Error::InvalidValue(err.to_string()))?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+}
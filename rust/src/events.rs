@@ -0,0 +1,178 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::runnable::LinkVertex;
+
+/// Something that happened in the editor, delivered to the host page.
+#[derive(Serialize, Clone, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GraphEvent {
+    NodeAdded {
+        id: u16,
+        name: String,
+    },
+    NodeRemoved {
+        id: u16,
+        name: String,
+    },
+    LinkAdded {
+        start: LinkVertex,
+        end: LinkVertex,
+    },
+    LinkRemoved {
+        start: LinkVertex,
+        end: LinkVertex,
+    },
+    RunStarted {
+        iterations: u32,
+    },
+    RunFinished {
+        iterations: u32,
+        /// Seconds.
+        duration: f64,
+        errors: usize,
+    },
+    NodeError {
+        id: u16,
+        name: String,
+        message: String,
+    },
+    /// The project changed since it was last saved or copied as JSON, or was
+    /// just saved. The periodic autosave of the editor state doesn't count.
+    ProjectDirty {
+        is_dirty: bool,
+    },
+}
+
+impl GraphEvent {
+    /// Same as the `type` field of the serialized event.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraphEvent::NodeAdded { .. } => "nodeAdded",
+            GraphEvent::NodeRemoved { .. } => "nodeRemoved",
+            GraphEvent::LinkAdded { .. } => "linkAdded",
+            GraphEvent::LinkRemoved { .. } => "linkRemoved",
+            GraphEvent::RunStarted { .. } => "runStarted",
+            GraphEvent::RunFinished { .. } => "runFinished",
+            GraphEvent::NodeError { .. } => "nodeError",
+            GraphEvent::ProjectDirty { .. } => "projectDirty",
+        }
+    }
+}
+
+/// What the tracker compares between frames.
+pub struct GraphState {
+    /// Names by node id.
+    pub nodes: IndexMap<u16, String>,
+    pub links: Vec<(LinkVertex, LinkVertex)>,
+    /// Hash of everything that gets saved, see [`fingerprint`].
+    pub fingerprint: u64,
+}
+
+/// Turns the difference between the graph of two frames into events and
+/// queues them with the ones pushed directly.
+#[derive(Default)]
+pub struct EventTracker {
+    previous: Option<GraphState>,
+    saved_fingerprint: u64,
+    is_dirty: bool,
+    pending: Vec<GraphEvent>,
+}
+
+impl EventTracker {
+    pub fn push(&mut self, event: GraphEvent) {
+        self.pending.push(event);
+    }
+
+    /// The first state observed is the loaded project and produces no events.
+    pub fn observe(&mut self, state: GraphState) {
+        let Some(previous) = self.previous.take() else {
+            self.saved_fingerprint = state.fingerprint;
+            self.previous = Some(state);
+            return;
+        };
+
+        for (id, name) in previous.nodes.iter() {
+            if !state.nodes.contains_key(id) {
+                self.pending.push(GraphEvent::NodeRemoved {
+                    id: *id,
+                    name: name.clone(),
+                });
+            }
+        }
+        for (id, name) in state.nodes.iter() {
+            if !previous.nodes.contains_key(id) {
+                self.pending.push(GraphEvent::NodeAdded {
+                    id: *id,
+                    name: name.clone(),
+                });
+            }
+        }
+        let is_same = |a: &(LinkVertex, LinkVertex), b: &(LinkVertex, LinkVertex)| {
+            a.0.function_id == b.0.function_id
+                && a.0.param_id == b.0.param_id
+                && a.1.function_id == b.1.function_id
+                && a.1.param_id == b.1.param_id
+        };
+        for link in previous.links.iter() {
+            if !state.links.iter().any(|other| is_same(link, other)) {
+                self.pending.push(GraphEvent::LinkRemoved {
+                    start: link.0.clone(),
+                    end: link.1.clone(),
+                });
+            }
+        }
+        for link in state.links.iter() {
+            if !previous.links.iter().any(|other| is_same(link, other)) {
+                self.pending.push(GraphEvent::LinkAdded {
+                    start: link.0.clone(),
+                    end: link.1.clone(),
+                });
+            }
+        }
+
+        let is_dirty = state.fingerprint != self.saved_fingerprint;
+        if is_dirty != self.is_dirty {
+            self.is_dirty = is_dirty;
+            self.pending.push(GraphEvent::ProjectDirty { is_dirty });
+        }
+        self.previous = Some(state);
+    }
+
+    /// The last observed state is what got saved.
+    pub fn mark_saved(&mut self) {
+        if let Some(previous) = &self.previous {
+            self.saved_fingerprint = previous.fingerprint;
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<GraphEvent> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Hashes the parts of a project a user would expect a save for.
+pub fn fingerprint(parts: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hands events to the listeners of the host page. Natively nobody listens.
+pub fn dispatch(events: Vec<GraphEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    #[cfg(target_arch = "wasm32")]
+    crate::web_api::emit(&events);
+    #[cfg(not(target_arch = "wasm32"))]
+    for event in events {
+        log::debug!("{}", event.name());
+    }
+}
@@ -0,0 +1,608 @@
+//! Runs graphs away from the UI thread: in a Web Worker on the web and on a
+//! background thread natively. Both only exchange [`ExecutionRequest`]s and
+//! [`ExecutionMessage`]s serialized to JSON, so the thread gets a serialized
+//! graph and sends back serialized progress and results the same way the
+//! worker does.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rhai::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::Graph;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pool::{PoolRunner, ThreadPool};
+use crate::trace::{self, TraceEntry, MAX_TRACE_ENTRIES};
+
+/// Seconds between two progress messages.
+const PROGRESS_INTERVAL: f64 = 0.05;
+
+/// What happens when the graph is edited while it runs.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum EditPolicy {
+    /// Drop the run and start over with the edited graph.
+    #[default]
+    Restart,
+    /// Let the run finish, then run the edited graph.
+    Queue,
+}
+
+impl EditPolicy {
+    pub const ALL: [EditPolicy; 2] = [EditPolicy::Restart, EditPolicy::Queue];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditPolicy::Restart => "Restart",
+            EditPolicy::Queue => "Queue",
+        }
+    }
+}
+
+/// What a run covers.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug, Default)]
+pub enum RunScope {
+    /// Every function in execution order, latching delays after each iteration.
+    #[default]
+    Graph,
+    /// Only these functions, once and in this order: the editor re-running
+    /// what an edit affects, or the debugger stepping. Delays latch at the end
+    /// when `latch_delays` is set.
+    Functions { ids: Vec<u16>, latch_delays: bool },
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ExecutionRequest {
+    pub run_id: u64,
+    pub graph: Graph,
+    pub iterations: u32,
+    #[serde(default)]
+    pub scope: RunScope,
+}
+
+/// Externally tagged: the graph keys functions by number, which an internally
+/// tagged enum can't read back from JSON.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ExecutionMessage {
+    Progress {
+        run_id: u64,
+        /// Function runs done so far, out of `total`.
+        completed: u32,
+        total: u32,
+        /// Functions running right now.
+        executing: Vec<u16>,
+    },
+    Finished {
+        run_id: u64,
+        /// The graph with the values of the last iteration.
+        graph: Box<Graph>,
+        /// The last runs, `started_at` counted from the start of the run.
+        trace: Vec<TraceEntry>,
+    },
+    Failed {
+        run_id: u64,
+        message: String,
+    },
+}
+
+impl ExecutionMessage {
+    pub fn run_id(&self) -> u64 {
+        match self {
+            ExecutionMessage::Progress { run_id, .. }
+            | ExecutionMessage::Finished { run_id, .. }
+            | ExecutionMessage::Failed { run_id, .. } => *run_id,
+        }
+    }
+
+    /// A serialized [`ExecutionMessage::Failed`], for when the run itself
+    /// can't report anything.
+    fn failed_json(run_id: u64, message: String) -> String {
+        serde_json::to_string(&ExecutionMessage::Failed { run_id, message }).unwrap_or_default()
+    }
+}
+
+/// Runs the functions of one wave and hands their outputs on.
+pub trait WaveRunner {
+    /// Trace entries come back in the order of `wave`.
+    fn run_wave(&mut self, graph: &mut Graph, wave: &[u16]) -> Vec<TraceEntry>;
+}
+
+/// One function after another on the calling thread.
+impl WaveRunner for Engine {
+    fn run_wave(&mut self, graph: &mut Graph, wave: &[u16]) -> Vec<TraceEntry> {
+        let mut entries = Vec::with_capacity(wave.len());
+        graph.run_functions(self, wave, |entry| entries.push(entry));
+        entries
+    }
+}
+
+/// Steps the graph of `request` wave by wave and reports through `post`. This
+/// is what runs inside the worker or the background thread. Stops without a
+/// word once `cancel` is set.
+pub fn execute(
+    request: ExecutionRequest,
+    runner: &mut impl WaveRunner,
+    cancel: &AtomicBool,
+    mut post: impl FnMut(ExecutionMessage),
+) {
+    let ExecutionRequest {
+        run_id,
+        mut graph,
+        iterations,
+        scope,
+    } = request;
+    let (order, latch_delays) = match scope {
+        RunScope::Graph => match graph.execution_order() {
+            Some(order) => (order, true),
+            None => {
+                post(ExecutionMessage::Failed {
+                    run_id,
+                    message: "Links form a cycle, break it with a delay".to_owned(),
+                });
+                return;
+            }
+        },
+        RunScope::Functions { ids, latch_delays } => (ids, latch_delays),
+    };
+    let waves = graph.waves(&order);
+
+    let total = order.len() as u32 * iterations;
+    let started_at = trace::now();
+    let mut last_progress = f64::NEG_INFINITY;
+    let mut completed = 0;
+    let mut entries = VecDeque::new();
+    for _ in 0..iterations {
+        for wave in waves.iter() {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            let now = trace::now();
+            if now - last_progress > PROGRESS_INTERVAL {
+                last_progress = now;
+                post(ExecutionMessage::Progress {
+                    run_id,
+                    completed,
+                    total,
+                    executing: wave.clone(),
+                });
+            }
+
+            for mut entry in runner.run_wave(&mut graph, wave) {
+                entry.started_at -= started_at;
+                entries.push_back(entry);
+            }
+            while entries.len() > MAX_TRACE_ENTRIES {
+                entries.pop_front();
+            }
+            completed += wave.len() as u32;
+        }
+        if latch_delays {
+            graph.latch_delays();
+        }
+    }
+    post(ExecutionMessage::Finished {
+        run_id,
+        graph: Box::new(graph),
+        trace: entries.into(),
+    });
+}
+
+/// [`execute`] over JSON, for the worker and the background thread.
+pub fn execute_json(
+    request_json: &str,
+    runner: &mut impl WaveRunner,
+    cancel: &AtomicBool,
+    mut post: impl FnMut(String),
+) {
+    let request: ExecutionRequest = match serde_json::from_str(request_json) {
+        Ok(request) => request,
+        Err(err) => {
+            // The id is all it takes for the editor to stop waiting.
+            let run_id = serde_json::from_str::<serde_json::Value>(request_json)
+                .ok()
+                .and_then(|request| request.get("run_id")?.as_u64())
+                .unwrap_or_default();
+            post(ExecutionMessage::failed_json(
+                run_id,
+                format!("Unreadable run request: {err}"),
+            ));
+            return;
+        }
+    };
+    execute(
+        request,
+        runner,
+        cancel,
+        |message| match serde_json::to_string(&message) {
+            Ok(json) => post(json),
+            Err(err) => log::error!("Can't serialize the run: {err}"),
+        },
+    );
+}
+
+/// The run the executor waits for.
+#[derive(Clone, Debug)]
+pub struct RunState {
+    pub run_id: u64,
+    pub iterations: u32,
+    pub scope: RunScope,
+    /// Trace time the run was started at.
+    pub started_at: f64,
+    pub completed: u32,
+    pub total: u32,
+    /// Functions running as of the last progress message.
+    pub executing: Vec<u16>,
+}
+
+impl RunState {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.completed as f32 / self.total as f32
+        }
+    }
+}
+
+/// Starts runs off the UI thread and collects what they report.
+#[derive(Default)]
+pub struct BackgroundExecutor {
+    last_run_id: u64,
+    running: Option<RunState>,
+    /// Serialized [`ExecutionMessage`]s.
+    inbox: Arc<Mutex<Vec<String>>>,
+    /// Repainted when a message arrives, set on every [`Self::poll`].
+    ctx: Option<egui::Context>,
+    #[cfg(target_arch = "wasm32")]
+    worker: Option<web::Worker>,
+    /// Started with the first run.
+    #[cfg(not(target_arch = "wasm32"))]
+    pool: Option<Arc<ThreadPool>>,
+    /// Set to stop the current run.
+    #[cfg(not(target_arch = "wasm32"))]
+    cancel: Arc<AtomicBool>,
+}
+
+impl BackgroundExecutor {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn running(&self) -> Option<&RunState> {
+        self.running.as_ref()
+    }
+
+    /// Sends `graph` off to run `scope` `iterations` times. A run still going
+    /// is cancelled and its results are dropped.
+    pub fn start(&mut self, graph: Graph, iterations: u32, scope: RunScope) {
+        self.cancel();
+        self.last_run_id += 1;
+        let request = ExecutionRequest {
+            run_id: self.last_run_id,
+            graph,
+            iterations,
+            scope: scope.clone(),
+        };
+        self.running = Some(RunState {
+            run_id: request.run_id,
+            iterations,
+            scope,
+            started_at: trace::now(),
+            completed: 0,
+            total: 0,
+            executing: Vec::new(),
+        });
+
+        let Ok(request) = serde_json::to_string(&request) else {
+            log::error!("Can't serialize the graph to run");
+            self.running = None;
+            return;
+        };
+        let inbox = self.inbox.clone();
+        let ctx = self.ctx.clone();
+        let deliver = move |message: String| {
+            inbox.lock().unwrap().push(message);
+            if let Some(ctx) = &ctx {
+                ctx.request_repaint();
+            }
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            // A worker that failed to load or panicked can't run anything more.
+            if self.worker.as_ref().is_some_and(web::Worker::is_broken) {
+                self.worker = None;
+            }
+            if self.worker.is_none() {
+                self.worker = web::Worker::spawn(deliver.clone());
+            }
+            match &self.worker {
+                Some(worker) => worker.post(self.last_run_id, request),
+                // Without workers the run blocks the page, but still happens.
+                None => execute_json(
+                    &request,
+                    &mut Engine::new(),
+                    &AtomicBool::new(false),
+                    deliver,
+                ),
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let pool = self.pool.get_or_insert_with(Default::default).clone();
+            let cancel = Arc::new(AtomicBool::new(false));
+            self.cancel = cancel.clone();
+            let run_id = self.last_run_id;
+            std::thread::spawn(move || {
+                let mut runner = PoolRunner {
+                    pool: &pool,
+                    cancel: cancel.clone(),
+                };
+                let run = std::panic::AssertUnwindSafe(|| {
+                    execute_json(&request, &mut runner, &cancel, &deliver)
+                });
+                if std::panic::catch_unwind(run).is_err() {
+                    deliver(ExecutionMessage::failed_json(
+                        run_id,
+                        "The run stopped unexpectedly".to_owned(),
+                    ));
+                }
+            });
+        }
+    }
+
+    /// Stops the current run, if any, without waiting for it.
+    pub fn cancel(&mut self) {
+        if self.running.take().is_none() {
+            return;
+        }
+        // A busy worker can't be told to stop, so it is replaced.
+        #[cfg(target_arch = "wasm32")]
+        if let Some(worker) = self.worker.take() {
+            worker.terminate();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Finished and failed runs since the last call. Progress messages only
+    /// update [`Self::running`].
+    pub fn poll(&mut self, ctx: &egui::Context) -> Vec<(RunState, ExecutionMessage)> {
+        self.ctx = Some(ctx.clone());
+        let messages = std::mem::take(&mut *self.inbox.lock().unwrap());
+
+        let mut done = Vec::new();
+        for message in messages {
+            let message: ExecutionMessage = match serde_json::from_str(&message) {
+                Ok(message) => message,
+                Err(err) => {
+                    log::error!("Unreadable message from the executor: {err}");
+                    continue;
+                }
+            };
+            let Some(running) = self.running.as_mut() else {
+                continue;
+            };
+            if message.run_id() != running.run_id {
+                continue;
+            }
+            match message {
+                ExecutionMessage::Progress {
+                    completed,
+                    total,
+                    executing,
+                    ..
+                } => {
+                    running.completed = completed;
+                    running.total = total;
+                    running.executing = executing;
+                }
+                message => {
+                    if let Some(running) = self.running.take() {
+                        done.push((running, message));
+                    }
+                }
+            }
+        }
+        done
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use wasm_bindgen::prelude::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::ExecutionMessage;
+
+    #[wasm_bindgen(inline_js = r#"
+export function spawn_executor_worker() {
+    // Snippets live in `snippets/<crate>/`, next to the module two levels up.
+    const main = new URL("../../rust_scripter.js", import.meta.url).href;
+    // Errors are rethrown outside the async handler so that they reach the
+    // `error` event of the worker instead of becoming unhandled rejections.
+    const source = `
+        import init, { executeInWorker } from "${main}";
+        const ready = init();
+        self.onmessage = async (event) => {
+            try {
+                await ready;
+                executeInWorker(event.data, (message) => self.postMessage(message));
+            } catch (error) {
+                setTimeout(() => { throw error; });
+            }
+        };
+    `;
+    const url = URL.createObjectURL(new Blob([source], { type: "text/javascript" }));
+    return new Worker(url, { type: "module" });
+}
+"#)]
+    extern "C" {
+        #[wasm_bindgen(catch)]
+        fn spawn_executor_worker() -> Result<web_sys::Worker, JsValue>;
+    }
+
+    pub struct Worker {
+        worker: web_sys::Worker,
+        /// The run posted last, which an error of the worker fails.
+        run_id: Rc<Cell<u64>>,
+        is_broken: Rc<Cell<bool>>,
+        _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+        _on_error: Closure<dyn FnMut(web_sys::ErrorEvent)>,
+    }
+
+    impl Worker {
+        pub fn spawn(deliver: impl Fn(String) + 'static) -> Option<Self> {
+            let worker = spawn_executor_worker()
+                .map_err(|err| log::warn!("Can't start the executor worker: {err:?}"))
+                .ok()?;
+            let deliver = Rc::new(deliver);
+            let run_id = Rc::new(Cell::new(0));
+            let is_broken = Rc::new(Cell::new(false));
+
+            let on_message = {
+                let deliver = deliver.clone();
+                Closure::new(
+                    move |event: web_sys::MessageEvent| match event.data().as_string() {
+                        Some(message) => deliver(message),
+                        None => log::error!("Unexpected message from the executor worker"),
+                    },
+                )
+            };
+            let on_error = {
+                let run_id = run_id.clone();
+                let is_broken = is_broken.clone();
+                Closure::new(move |event: web_sys::ErrorEvent| {
+                    is_broken.set(true);
+                    let message = format!("The executor worker stopped: {}", event.message());
+                    deliver(ExecutionMessage::failed_json(run_id.get(), message));
+                })
+            };
+            worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+            Some(Self {
+                worker,
+                run_id,
+                is_broken,
+                _on_message: on_message,
+                _on_error: on_error,
+            })
+        }
+
+        pub fn is_broken(&self) -> bool {
+            self.is_broken.get()
+        }
+
+        pub fn terminate(&self) {
+            self.worker.terminate();
+        }
+
+        pub fn post(&self, run_id: u64, request_json: String) {
+            self.run_id.set(run_id);
+            if let Err(err) = self.worker.post_message(&JsValue::from_str(&request_json)) {
+                log::error!("Can't send the graph to the executor worker: {err:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphLink;
+    use crate::runnable::{LinkVertex, Runnable};
+
+    /// `Source` feeds a delay, which feeds `Sink`.
+    fn graph_with_delay() -> Graph {
+        let mut graph = Graph::default();
+        let source =
+            Runnable::with_signature("Source".to_owned(), "#{ Out: 7 }".to_owned(), &[], &["Out"]);
+        let delay = Runnable::delay("Delay".to_owned());
+        let sink = Runnable::with_signature(
+            "Sink".to_owned(),
+            "#{ Out: In }".to_owned(),
+            &["In"],
+            &["Out"],
+        );
+        let link = |start: (u16, &Runnable), end: (u16, &Runnable)| GraphLink {
+            start: LinkVertex {
+                function_id: start.0,
+                param_id: *start.1.outputs.keys().next().unwrap(),
+            },
+            end: LinkVertex {
+                function_id: end.0,
+                param_id: *end.1.inputs.keys().next().unwrap(),
+            },
+        };
+        graph.links = vec![
+            link((1, &source), (2, &delay)),
+            link((2, &delay), (3, &sink)),
+        ];
+        graph.functions.extend([(1, source), (2, delay), (3, sink)]);
+        graph
+    }
+
+    fn run(graph: Graph, scope: RunScope) -> Graph {
+        let request = ExecutionRequest {
+            run_id: 1,
+            graph,
+            iterations: 1,
+            scope,
+        };
+        let mut finished = None;
+        execute(
+            request,
+            &mut Engine::new(),
+            &AtomicBool::new(false),
+            |message| {
+                if let ExecutionMessage::Finished { graph, .. } = message {
+                    finished = Some(*graph);
+                }
+            },
+        );
+        finished.expect("The run finished")
+    }
+
+    #[test]
+    fn function_runs_leave_other_functions_and_delays_alone() {
+        let scope = RunScope::Functions {
+            ids: vec![1],
+            latch_delays: false,
+        };
+        let graph = run(graph_with_delay(), scope);
+
+        assert!(graph.functions[&1].outputs[0].last_value.is_some());
+        assert!(graph.functions[&2].state.is_none());
+        assert!(graph.functions[&3].outputs[0].last_value.is_none());
+    }
+
+    #[test]
+    fn unreadable_requests_fail_their_run() {
+        let mut messages = Vec::new();
+        execute_json(
+            r#"{ "run_id": 7, "graph": "not a graph" }"#,
+            &mut Engine::new(),
+            &AtomicBool::new(false),
+            |message| messages.push(message),
+        );
+
+        let [message] = messages.as_slice() else {
+            panic!("Expected one message, got {messages:?}");
+        };
+        let message: ExecutionMessage = serde_json::from_str(message).unwrap();
+        assert!(matches!(
+            message,
+            ExecutionMessage::Failed { run_id: 7, .. }
+        ));
+    }
+
+    #[test]
+    fn graph_runs_latch_delays() {
+        let graph = run(graph_with_delay(), RunScope::Graph);
+        assert_eq!(graph.functions[&2].state.as_ref().unwrap().as_int(), Ok(7));
+    }
+}
@@ -0,0 +1,60 @@
+/// Hands `contents` to the user as a file called `name`: a download on the
+/// web, a file in the working directory natively.
+pub fn save(name: &str, contents: &str) {
+    #[cfg(target_arch = "wasm32")]
+    if let Err(err) = download(name, contents) {
+        log::error!("Can't download {name}: {err:?}");
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    match std::fs::write(name, contents) {
+        Ok(()) => log::info!("Saved {name}"),
+        Err(err) => log::error!("Can't save {name}: {err}"),
+    }
+}
+
+/// Name and contents of the files with this extension dropped on the window
+/// this frame.
+pub fn dropped(ctx: &egui::Context, extension: &str) -> Vec<(String, String)> {
+    let files = ctx.input(|i| i.raw.dropped_files.clone());
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let name = match file.path {
+                Some(ref path) => path.file_name()?.to_string_lossy().into_owned(),
+                None => file.name.clone(),
+            };
+            if !name.ends_with(extension) {
+                return None;
+            }
+            let contents = match (file.bytes, file.path) {
+                (Some(bytes), _) => String::from_utf8_lossy(&bytes).into_owned(),
+                (None, Some(path)) => match std::fs::read_to_string(&path) {
+                    Ok(contents) => contents,
+                    Err(err) => {
+                        log::error!("Can't read {name}: {err}");
+                        return None;
+                    }
+                },
+                (None, None) => return None,
+            };
+            Some((name, contents))
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, contents: &str) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast as _;
+
+    let parts = js_sys::Array::of1(&contents.into());
+    let blob = web_sys::Blob::new_with_str_sequence(&parts)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url)
+}
@@ -0,0 +1,68 @@
+use egui::{Align2, Color32, FontId, Painter, Pos2, Rounding, Stroke};
+use rhai::Dynamic;
+
+use crate::routing;
+
+/// How long a pulse takes to travel along a link, in seconds.
+pub const PULSE_DURATION: f64 = 0.6;
+const PULSE_RADIUS: f32 = 4.0;
+const MAX_PREVIEW_CHARS: usize = 16;
+
+/// Color of a link carrying `value`, by its type. `None` keeps the default stroke.
+pub fn value_color(value: &Dynamic) -> Option<Color32> {
+    let color = if value.is_int() {
+        Color32::from_rgb(90, 150, 230)
+    } else if value.is_float() {
+        Color32::from_rgb(90, 190, 120)
+    } else if value.is_bool() {
+        Color32::from_rgb(220, 90, 90)
+    } else if value.is_string() || value.is_char() {
+        Color32::from_rgb(230, 160, 70)
+    } else if value.is_array() || value.is_blob() {
+        Color32::from_rgb(70, 190, 190)
+    } else if value.is_map() {
+        Color32::from_rgb(170, 110, 210)
+    } else {
+        return None;
+    };
+    Some(color)
+}
+
+/// Short one-line text of `value`, cut with an ellipsis when too long.
+pub fn value_preview(value: &Dynamic) -> String {
+    let text = value.to_string().replace('\n', " ");
+    if text.chars().count() > MAX_PREVIEW_CHARS {
+        let cut: String = text.chars().take(MAX_PREVIEW_CHARS - 1).collect();
+        format!("{cut}…")
+    } else {
+        text
+    }
+}
+
+pub fn paint_preview(painter: &Painter, pos: Pos2, text: String, color: Color32) {
+    let galley = painter.layout_no_wrap(text, FontId::monospace(11.0), Color32::BLACK);
+    let rect = Align2::CENTER_CENTER
+        .anchor_size(pos, galley.size())
+        .expand2(egui::vec2(3.0, 1.0));
+    painter.rect(
+        rect,
+        Rounding::same(3.0),
+        Color32::LIGHT_GRAY,
+        Stroke::new(1.0_f32, color),
+    );
+    painter.galley(rect.min + egui::vec2(3.0, 1.0), galley, Color32::BLACK);
+}
+
+/// Paints a dot travelling along `path` that started at `started_at`.
+/// Returns `false` once the pulse reached the end.
+pub fn paint_pulse(painter: &Painter, path: &[Pos2], started_at: f64, color: Color32) -> bool {
+    let elapsed = crate::trace::now() - started_at;
+    if !(0.0..PULSE_DURATION).contains(&elapsed) {
+        return false;
+    }
+    let fraction = (elapsed / PULSE_DURATION) as f32;
+    let pos = routing::point_at(path, fraction);
+    painter.circle_filled(pos, PULSE_RADIUS * 2.0, color.gamma_multiply(0.3));
+    painter.circle_filled(pos, PULSE_RADIUS, color);
+    true
+}
@@ -0,0 +1,273 @@
+use egui::{Area, Frame, Id, Key, KeyboardShortcut, Modifiers, Order, Pos2, ScrollArea, Ui};
+use rhai::Engine;
+
+use super::Runnable;
+
+const MAX_VISIBLE_COMPLETIONS: usize = 12;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CompletionKind {
+    Input,
+    Output,
+    Function,
+    Module,
+}
+
+impl CompletionKind {
+    fn tag(&self) -> &'static str {
+        match self {
+            CompletionKind::Input => "in",
+            CompletionKind::Output => "out",
+            CompletionKind::Function => "fn",
+            CompletionKind::Module => "mod",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub text: String,
+    pub detail: String,
+    pub kind: CompletionKind,
+}
+
+/// State of an open completion popup. Indexes are in chars, not bytes,
+/// to match the text edit cursor.
+#[derive(Debug, Default)]
+pub struct CompletionOptions {
+    pub prefix_start: usize,
+    pub cursor: usize,
+    pub prefix: String,
+    pub candidates: Vec<Completion>,
+    pub selected: usize,
+}
+
+impl CompletionOptions {
+    fn matching(&self) -> impl Iterator<Item = &Completion> {
+        let prefix = self.prefix.to_lowercase();
+        self.candidates
+            .iter()
+            .filter(move |candidate| candidate.text.to_lowercase().starts_with(&prefix))
+    }
+
+    fn selected_completion(&self) -> Option<Completion> {
+        self.matching().nth(self.selected).cloned()
+    }
+}
+
+/// Key presses the popup takes away from the text edit before it sees them.
+#[derive(Default)]
+pub struct CompletionKeys {
+    pub requested: bool,
+    pub accepted: bool,
+}
+
+/// Gathers everything that can be completed inside the code of `runnable`:
+/// its parameters, then functions registered in `engine` (module exports included).
+pub fn collect_candidates(runnable: &Runnable, engine: &Engine) -> Vec<Completion> {
+    let mut candidates: Vec<Completion> = runnable
+        .inputs
+        .values()
+        .map(|input| Completion {
+            text: input.param_name.clone(),
+            detail: "input".to_owned(),
+            kind: CompletionKind::Input,
+        })
+        .chain(runnable.outputs.values().map(|output| Completion {
+            text: output.param_name.clone(),
+            detail: "output key".to_owned(),
+            kind: CompletionKind::Output,
+        }))
+        .collect();
+
+    let mut functions: Vec<Completion> = engine
+        .gen_fn_signatures(true)
+        .into_iter()
+        .filter_map(|signature| {
+            let name = signature.split('(').next()?.trim().to_owned();
+            let is_identifier = name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+                && name.starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if !is_identifier {
+                return None;
+            }
+            let kind = if name.contains("::") {
+                CompletionKind::Module
+            } else {
+                CompletionKind::Function
+            };
+            Some(Completion {
+                text: name,
+                detail: signature,
+                kind,
+            })
+        })
+        .collect();
+    functions.sort_by(|a, b| a.text.cmp(&b.text));
+
+    for function in functions {
+        if !candidates.iter().any(|c| c.text == function.text) {
+            candidates.push(function);
+        }
+    }
+    candidates
+}
+
+/// Returns the char index where the identifier ending at `cursor` starts, and the identifier itself.
+pub fn word_before(text: &str, cursor: usize) -> (usize, String) {
+    let chars: Vec<char> = text.chars().take(cursor).collect();
+    let start = chars
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_' || *c == ':'))
+        .map_or(0, |pos| pos + 1);
+    (start, chars[start..].iter().collect())
+}
+
+fn char_to_byte(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(byte, _)| byte)
+}
+
+/// Takes the popup keys out of the input queue while the editor `editor_id` is focused.
+pub fn consume_keys(
+    ui: &mut Ui,
+    editor_id: Id,
+    trigger: Option<KeyboardShortcut>,
+    completion: &mut Option<CompletionOptions>,
+) -> CompletionKeys {
+    let mut keys = CompletionKeys::default();
+    if !ui.memory(|mem| mem.has_focus(editor_id)) {
+        return keys;
+    }
+
+    ui.input_mut(|i| {
+        keys.requested = trigger.is_some_and(|trigger| i.consume_shortcut(&trigger));
+
+        if let Some(options) = completion {
+            let count = options.matching().count();
+            if i.consume_key(Modifiers::NONE, Key::ArrowDown) && count > 0 {
+                options.selected = (options.selected + 1) % count;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowUp) && count > 0 {
+                options.selected = (options.selected + count - 1) % count;
+            }
+            if i.consume_key(Modifiers::NONE, Key::Enter)
+                || i.consume_key(Modifiers::NONE, Key::Tab)
+            {
+                keys.accepted = true;
+            }
+            if i.consume_key(Modifiers::NONE, Key::Escape) {
+                *completion = None;
+            }
+        }
+    });
+    keys
+}
+
+/// Opens, refreshes, renders and applies the completion popup of the code editor.
+pub fn show_completion(
+    ui: &mut Ui,
+    editor_id: Id,
+    output: &egui::text_edit::TextEditOutput,
+    keys: CompletionKeys,
+    runnable: &mut Runnable,
+    engine: &Engine,
+    completion: &mut Option<CompletionOptions>,
+) {
+    if let Some(cursor_range) = output.cursor_range {
+        let cursor = cursor_range.primary.ccursor.index;
+        let (prefix_start, prefix) = word_before(&runnable.code, cursor);
+
+        let typed_identifier = output.response.changed() && !prefix.is_empty();
+        if completion.is_none() && (keys.requested || typed_identifier) {
+            *completion = Some(CompletionOptions {
+                candidates: collect_candidates(runnable, engine),
+                ..Default::default()
+            });
+        }
+
+        if let Some(options) = completion {
+            if prefix.is_empty() && !keys.requested && options.cursor != cursor {
+                *completion = None;
+                return;
+            }
+            if options.prefix != prefix {
+                options.selected = 0;
+            }
+            options.prefix_start = prefix_start;
+            options.cursor = cursor;
+            options.prefix = prefix;
+            if options.matching().next().is_none() {
+                *completion = None;
+                return;
+            }
+        }
+    }
+
+    let Some(options) = completion else {
+        return;
+    };
+
+    let popup_pos = output
+        .cursor_range
+        .map(|range| {
+            let cursor_rect = output.galley.pos_from_cursor(&range.primary);
+            output.galley_pos + cursor_rect.left_bottom().to_vec2()
+        })
+        .unwrap_or(Pos2 {
+            x: output.response.rect.left(),
+            y: output.response.rect.bottom(),
+        });
+
+    let mut clicked = None;
+    Area::new(editor_id.with("completion"))
+        .order(Order::Foreground)
+        .fixed_pos(popup_pos)
+        .constrain(true)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ScrollArea::vertical()
+                    .max_height(ui.spacing().interact_size.y * MAX_VISIBLE_COMPLETIONS as f32)
+                    .show(ui, |ui| {
+                        for (index, candidate) in options.matching().enumerate() {
+                            let text = format!("{}  {}", candidate.kind.tag(), candidate.text);
+                            let response = ui
+                                .selectable_label(index == options.selected, text)
+                                .on_hover_text(&candidate.detail);
+                            if index == options.selected {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                clicked = Some(index);
+                            }
+                        }
+                    });
+            });
+        });
+
+    if let Some(index) = clicked {
+        options.selected = index;
+    }
+    if keys.accepted || clicked.is_some() {
+        if let Some(accepted) = options.selected_completion() {
+            let start = char_to_byte(&runnable.code, options.prefix_start);
+            let end = char_to_byte(&runnable.code, options.cursor);
+            runnable.code.replace_range(start..end, &accepted.text);
+
+            let new_cursor = options.prefix_start + accepted.text.chars().count();
+            let mut state = output.state.clone();
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(
+                    egui::text::CCursor::new(new_cursor),
+                )));
+            state.store(ui.ctx(), editor_id);
+            ui.memory_mut(|mem| mem.request_focus(editor_id));
+        }
+        *completion = None;
+    } else if !ui.memory(|mem| mem.has_focus(editor_id)) {
+        *completion = None;
+    }
+}
@@ -1,148 +1,31 @@
 use egui::{
-    vec2, widgets::Widget, Align, Align2, Area, Button, Color32, Frame, Id, Key, KeyboardShortcut,
-    Label, LayerId, Modifiers, Order, Pos2, Rect, Response, Rounding, Sense, TextEdit, TextStyle,
-    Ui, Vec2, Window,
+    vec2, widgets::Widget, Align, Align2, Area, Button, Color32, Frame, Id, Label, LayerId, Order,
+    Pos2, Rect, Response, Rounding, Sense, Stroke, TextEdit, TextStyle, Ui, Vec2, WidgetInfo,
+    WidgetType, Window,
 };
-use indexmap::IndexMap;
-use rhai::{Dynamic, Engine, Map};
+use rhai::Dynamic;
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
-pub enum ParamType {
-    Input,
-    Output,
-}
+use crate::keymap::{self, WidgetShortcuts};
+use crate::runnable::{FunctionParam, LinkVertex, Runnable, RunnableKind};
+use crate::trace::TraceEntry;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct LinkVertex {
-    pub function_id: u16,
-    pub param_id: u16,
-}
+mod completion;
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct FunctionParam {
-    pub param_name: String,
-    pub type_name: String,
-    pub pos: Pos2,
-    pub should_be_deleted: bool,
-    pub is_renaming: bool,
-    pub is_editing: bool,
-    pub last_value: Option<rhai::Dynamic>,
-}
+use completion::CompletionOptions;
 
-impl Default for FunctionParam {
-    fn default() -> Self {
-        Self {
-            param_name: "New...".to_string(),
-            type_name: "String".to_string(),
-            pos: Pos2::default(),
-            should_be_deleted: false,
-            is_renaming: false,
-            is_editing: false,
-            last_value: None,
-        }
-    }
-}
+/// Side of the square around a port that reacts to the pointer.
+pub const DEFAULT_PORT_HIT_SIZE: f32 = 12.0;
 
-impl FunctionParam {
-    fn default_with_name(name: &str) -> Self {
-        Self {
-            param_name: name.to_string(),
-            type_name: "String".to_string(),
-            pos: Pos2::default(),
-            should_be_deleted: false,
-            is_renaming: false,
-            is_editing: false,
-            last_value: None,
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Runnable {
-    pub name: String,
-    pub code: String,
-    pub inputs: IndexMap<u16, FunctionParam>,
-    pub outputs: IndexMap<u16, FunctionParam>,
-}
-
-impl Default for Runnable {
-    fn default() -> Self {
-        let inputs = IndexMap::from_iter([
-            (
-                fastrand::u16(..),
-                FunctionParam::default_with_name("Input1"),
-            ),
-            (
-                fastrand::u16(..),
-                FunctionParam::default_with_name("Input2"),
-            ),
-            (
-                fastrand::u16(..),
-                FunctionParam::default_with_name("Input3"),
-            ),
-        ]);
-
-        let outputs = IndexMap::from_iter([
-            (
-                fastrand::u16(..),
-                FunctionParam::default_with_name("Output1"),
-            ),
-            (
-                fastrand::u16(..),
-                FunctionParam::default_with_name("Output2"),
-            ),
-        ]);
-        Self {
-            name: "Function #0".to_owned(),
-            code: r#"let val = #{Output1: Input1, Output2: Input2};
-val"#
-                .to_string(),
-            inputs,
-            outputs,
-        }
-    }
+fn default_port_hit_size() -> f32 {
+    DEFAULT_PORT_HIT_SIZE
 }
 
-impl Runnable {
-    pub fn get_param_by_vertex(&self, vertex: &LinkVertex) -> Pos2 {
-        if let Some(input) = self.inputs.get(&vertex.param_id) {
-            return input.pos;
-        }
-        if let Some(output) = self.outputs.get(&vertex.param_id) {
-            return output.pos;
-        }
-        panic!(
-            "No vertex found with {}, {}",
-            vertex.function_id, vertex.param_id
-        )
-    }
-
-    pub fn run(&mut self, engine: &Engine) {
-        let prepend_code = format!(
-            "{}{}{}",
-            "let ",
-            self.inputs
-                .iter()
-                .map(|input| format!(
-                    "{} = {}",
-                    input.1.param_name.clone(),
-                    input.1.last_value.clone().unwrap_or("3".into()).to_string()
-                ))
-                .collect::<Vec<String>>()
-                .join("; let "),
-            ";"
-        );
-        if let Ok(result) = engine.eval::<Map>(format!("{} {}", prepend_code, &self.code).as_str())
-        {
-            for ele in self.outputs.iter_mut() {
-                if let Some(val) = result.get(ele.1.param_name.as_str()) {
-                    ele.1.last_value = Some(val.clone());
-                }
-            }
-        }
-    }
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub enum ParamType {
+    Input,
+    Output,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -188,9 +71,51 @@ pub struct FunctionWidget {
     pub is_collapsed: bool,
     pub has_vertex: Option<LinkVertex>,
     pub mode: WidgetMode,
+    #[serde(default)]
+    pub has_breakpoint: bool,
     // Temp values
     pub rename_options: Option<RenameOptions>,
     pub edit_options: Option<EditOptions>,
+    #[serde(skip)]
+    pub completion: Option<CompletionOptions>,
+    #[serde(skip)]
+    pub is_current: bool,
+    /// Running in the background right now.
+    #[serde(skip)]
+    pub is_executing: bool,
+    #[serde(skip)]
+    pub is_dirty: bool,
+    #[serde(skip)]
+    pub inspect_request: Option<u16>,
+    /// Asks the app to run this function and the ones downstream of it.
+    #[serde(skip)]
+    pub run_request: bool,
+    #[serde(skip)]
+    pub link_drag_started: bool,
+    #[serde(skip)]
+    pub is_selected: bool,
+    #[serde(skip)]
+    pub was_clicked: bool,
+    /// Size of the window on the last frame.
+    #[serde(skip)]
+    pub size: Vec2,
+    #[serde(skip)]
+    pub move_to: Option<Pos2>,
+    /// Brings the window in front of the others on the next frame.
+    #[serde(skip)]
+    pub focus_request: bool,
+    /// Port with keyboard focus on the last frame.
+    #[serde(skip)]
+    pub focused_port: Option<u16>,
+    #[serde(skip, default = "default_port_hit_size")]
+    pub port_hit_size: f32,
+    #[serde(skip)]
+    pub shortcuts: WidgetShortcuts,
+    /// Where the window was when the user started dragging it.
+    #[serde(skip)]
+    pub drag_start_position: Option<Pos2>,
+    #[serde(skip)]
+    pub pending_trace: Vec<TraceEntry>,
     // Engine to run the code
     #[serde(skip, default = "rhai::Engine::new")]
     pub engine: rhai::Engine,
@@ -212,6 +137,41 @@ impl FunctionWidget {
         def
     }
 
+    pub fn delay_with_pos(initial_pos: Pos2, name: String) -> Self {
+        FunctionWidget::from_runnable(Runnable::delay(name), initial_pos)
+    }
+
+    /// An open widget for `runnable`, sized for its kind.
+    pub fn from_runnable(runnable: Runnable, initial_pos: Pos2) -> Self {
+        let is_delay = runnable.is_delay();
+        let mut widget = FunctionWidget::new(runnable, initial_pos, true, false);
+        if is_delay {
+            widget.interactive_size = Vec2 { x: 160.0, y: 40.0 };
+        }
+        widget
+    }
+
+    /// Moves the window on the next frame.
+    pub fn move_to(&mut self, position: Pos2) {
+        self.position = position;
+        self.move_to = Some(position);
+    }
+
+    /// Size of the window, or the size it is going to have if it wasn't shown yet.
+    pub fn layout_size(&self) -> Vec2 {
+        if self.size != Vec2::ZERO {
+            self.size
+        } else if self.mode == WidgetMode::Signature {
+            self.interactive_size
+        } else {
+            self.code_size
+        }
+    }
+
+    pub fn is_delay(&self) -> bool {
+        self.runnable.is_delay()
+    }
+
     pub fn new(runnable: Runnable, initial_pos: Pos2, is_open: bool, is_collapsed: bool) -> Self {
         Self {
             id: fastrand::u16(..),
@@ -225,6 +185,24 @@ impl FunctionWidget {
             mode: WidgetMode::Signature,
             rename_options: None,
             edit_options: None,
+            completion: None,
+            is_current: false,
+            is_executing: false,
+            is_dirty: false,
+            inspect_request: None,
+            run_request: false,
+            link_drag_started: false,
+            is_selected: false,
+            was_clicked: false,
+            size: Vec2::ZERO,
+            move_to: None,
+            focus_request: false,
+            focused_port: None,
+            port_hit_size: DEFAULT_PORT_HIT_SIZE,
+            shortcuts: WidgetShortcuts::default(),
+            drag_start_position: None,
+            pending_trace: Vec::new(),
+            has_breakpoint: false,
             engine: rhai::Engine::new(),
         }
     }
@@ -234,7 +212,12 @@ impl Widget for &mut FunctionWidget {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         let mut window = Window::new(&self.runnable.name)
             .open(&mut self.is_open)
-            .collapsible(true);
+            .collapsible(true)
+            .default_pos(self.position);
+
+        if let Some(position) = self.move_to.take() {
+            window = window.current_pos(position);
+        }
 
         if self.mode == WidgetMode::Signature {
             window = window.fixed_size(self.interactive_size);
@@ -242,6 +225,18 @@ impl Widget for &mut FunctionWidget {
             window = window.fixed_size(self.code_size);
         }
 
+        if self.is_current || self.has_breakpoint || self.is_selected {
+            let color = if self.is_current {
+                Color32::YELLOW
+            } else if self.has_breakpoint {
+                Color32::from_rgb(200, 0, 0)
+            } else {
+                Color32::LIGHT_BLUE
+            };
+            let frame = Frame::window(&ui.ctx().style()).stroke(Stroke::new(2.0_f32, color));
+            window = window.frame(frame);
+        }
+
         self.runnable
             .inputs
             .retain(|_, input| !input.should_be_deleted);
@@ -277,12 +272,27 @@ impl Widget for &mut FunctionWidget {
             .retain(|_, output| !output.should_be_deleted);
 
         let pointer = ui.ctx().pointer_latest_pos();
+        let mut should_run = false;
+        let mut should_focus_port = self.focus_request;
+        self.focused_port = None;
 
         let window_response = window
             .show(ui.ctx(), |ui| {
+                let is_delay = self.runnable.kind == RunnableKind::Delay;
                 ui.horizontal(|ui| {
-                    ui.selectable_value(&mut self.mode, WidgetMode::Signature, "Signature");
-                    ui.selectable_value(&mut self.mode, WidgetMode::Code, "Code");
+                    if is_delay {
+                        ui.label("Delay");
+                    } else {
+                        ui.selectable_value(&mut self.mode, WidgetMode::Signature, "Signature");
+                        ui.selectable_value(&mut self.mode, WidgetMode::Code, "Code");
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                        ui.toggle_value(&mut self.has_breakpoint, "🔴")
+                            .on_hover_text("Breakpoint");
+                        if self.is_executing {
+                            ui.add(egui::Spinner::new()).on_hover_text("Running");
+                        }
+                    });
                 });
 
                 if self.mode == WidgetMode::Code {
@@ -306,14 +316,36 @@ impl Widget for &mut FunctionWidget {
                         ui.fonts(|f| f.layout_job(layout_job))
                     };
 
-                    ui.add(
-                        egui::TextEdit::multiline(&mut self.runnable.code)
-                            .font(egui::TextStyle::Monospace) // for cursor height
-                            .code_editor()
-                            .desired_rows(10)
-                            .lock_focus(true)
-                            .desired_width(f32::INFINITY)
-                            .layouter(&mut layouter),
+                    let editor_id = ui.make_persistent_id((self.id, "code_editor"));
+                    let completion_keys = completion::consume_keys(
+                        ui,
+                        editor_id,
+                        self.shortcuts.trigger_completion,
+                        &mut self.completion,
+                    );
+
+                    let editor_output = egui::TextEdit::multiline(&mut self.runnable.code)
+                        .id(editor_id)
+                        .font(egui::TextStyle::Monospace) // for cursor height
+                        .code_editor()
+                        .desired_rows(10)
+                        .lock_focus(true)
+                        .desired_width(f32::INFINITY)
+                        .layouter(&mut layouter)
+                        .show(ui);
+
+                    if editor_output.response.changed() {
+                        self.is_dirty = true;
+                    }
+
+                    completion::show_completion(
+                        ui,
+                        editor_id,
+                        &editor_output,
+                        completion_keys,
+                        &mut self.runnable,
+                        &self.engine,
+                        &mut self.completion,
                     );
                 } else {
                     let stroke = ui.visuals().widgets.hovered.bg_stroke;
@@ -328,6 +360,7 @@ impl Widget for &mut FunctionWidget {
                                     input,
                                     input_id,
                                     ParamType::Input,
+                                    self.port_hit_size,
                                 );
                                 let label_response = label_row.0;
                                 let circle_response = label_row.1;
@@ -335,6 +368,12 @@ impl Widget for &mut FunctionWidget {
 
                                 paint_circle(&columns[0], &circle_rect);
                                 input.pos = circle_rect.center();
+                                track_port_focus(
+                                    &circle_response,
+                                    *input_id,
+                                    &mut should_focus_port,
+                                    &mut self.focused_port,
+                                );
 
                                 let value_changed = paint_last_value(
                                     &columns[0],
@@ -342,6 +381,7 @@ impl Widget for &mut FunctionWidget {
                                     circle_rect,
                                     &mut self.edit_options,
                                     ParamType::Input,
+                                    &self.shortcuts,
                                 );
 
                                 if (label_response.clicked() || circle_response.clicked())
@@ -352,6 +392,15 @@ impl Widget for &mut FunctionWidget {
                                         param_id: *input_id,
                                     });
                                 }
+                                if (label_response.drag_started() || circle_response.drag_started())
+                                    && !input.is_renaming
+                                {
+                                    self.has_vertex = Some(LinkVertex {
+                                        function_id: self.id,
+                                        param_id: *input_id,
+                                    });
+                                    self.link_drag_started = true;
+                                }
 
                                 add_label_behavoir(
                                     &mut columns[0],
@@ -359,6 +408,7 @@ impl Widget for &mut FunctionWidget {
                                     &mut self.rename_options,
                                     &label_response,
                                     input,
+                                    &self.shortcuts,
                                 );
                                 if label_response.hovered() || circle_response.hovered() {
                                     columns[0].painter().circle(
@@ -370,6 +420,10 @@ impl Widget for &mut FunctionWidget {
                                 }
 
                                 label_response.context_menu(|ui| {
+                                    if ui.button("Inspect").clicked() {
+                                        self.inspect_request = Some(*input_id);
+                                        ui.close_menu();
+                                    }
                                     if ui.button("Set constant").clicked() {
                                         input.is_editing = true;
                                         ui.memory_mut(|mem| mem.toggle_popup("constant_id".into()));
@@ -388,7 +442,7 @@ impl Widget for &mut FunctionWidget {
                                 value_changed || acc
                             },
                         );
-                        if columns[0].button("Add...").clicked() {
+                        if !is_delay && columns[0].button("Add...").clicked() {
                             self.runnable
                                 .inputs
                                 .insert(fastrand::u16(..), FunctionParam::default());
@@ -397,7 +451,10 @@ impl Widget for &mut FunctionWidget {
                         columns[1].with_layout(egui::Layout::top_down(Align::Center), |ui| {
                             let run_button_response = ui.add(run_button);
                             if run_button_response.clicked() {
-                                self.runnable.run(&self.engine);
+                                should_run = true;
+                            }
+                            if is_delay && ui.button("↺").on_hover_text("Reset state").clicked() {
+                                self.runnable.state = None;
                             }
                         });
                         for (output_id, output) in self.runnable.outputs.iter_mut() {
@@ -408,6 +465,7 @@ impl Widget for &mut FunctionWidget {
                                     output,
                                     output_id,
                                     ParamType::Output,
+                                    self.port_hit_size,
                                 );
                                 let label_response = label_row.0;
                                 let circle_response = label_row.1;
@@ -415,6 +473,12 @@ impl Widget for &mut FunctionWidget {
 
                                 paint_circle(ui, &circle_rect);
                                 output.pos = circle_rect.center();
+                                track_port_focus(
+                                    &circle_response,
+                                    *output_id,
+                                    &mut should_focus_port,
+                                    &mut self.focused_port,
+                                );
 
                                 paint_last_value(
                                     ui,
@@ -422,6 +486,7 @@ impl Widget for &mut FunctionWidget {
                                     circle_rect,
                                     &mut None,
                                     ParamType::Output,
+                                    &self.shortcuts,
                                 );
 
                                 if (label_response.clicked() || circle_response.clicked())
@@ -432,6 +497,15 @@ impl Widget for &mut FunctionWidget {
                                         param_id: *output_id,
                                     });
                                 }
+                                if (label_response.drag_started() || circle_response.drag_started())
+                                    && !output.is_renaming
+                                {
+                                    self.has_vertex = Some(LinkVertex {
+                                        function_id: self.id,
+                                        param_id: *output_id,
+                                    });
+                                    self.link_drag_started = true;
+                                }
 
                                 add_label_behavoir(
                                     ui,
@@ -439,6 +513,7 @@ impl Widget for &mut FunctionWidget {
                                     &mut self.rename_options,
                                     &label_response,
                                     output,
+                                    &self.shortcuts,
                                 );
 
                                 let is_circle_hovered =
@@ -453,6 +528,10 @@ impl Widget for &mut FunctionWidget {
                                 }
 
                                 label_response.context_menu(|ui| {
+                                    if ui.button("Inspect").clicked() {
+                                        self.inspect_request = Some(*output_id);
+                                        ui.close_menu();
+                                    }
                                     let btn = Button::new("Edit").shortcut_text("Double-click");
                                     if ui.add(btn).clicked() {
                                         output.is_renaming = true;
@@ -465,35 +544,41 @@ impl Widget for &mut FunctionWidget {
                                 });
                             });
                         }
-                        if columns[2].button("Add...").clicked() {
+                        if !is_delay && columns[2].button("Add...").clicked() {
                             self.runnable
                                 .outputs
                                 .insert(fastrand::u16(..), FunctionParam::default());
                         };
 
                         if value_changed {
-                            self.runnable.run(&self.engine);
+                            should_run = true;
                         }
                     });
                 }
             })
             .unwrap();
 
-        ui.input_mut(|i| {
-            if pointer.is_some()
-                && window_response.response.rect.contains(pointer.unwrap())
-                && i.consume_shortcut(&KeyboardShortcut::new(Modifiers::CTRL, Key::Q))
-            {
-                self.mode = if self.mode == WidgetMode::Signature {
-                    WidgetMode::Code
-                } else {
-                    WidgetMode::Signature
-                };
-            }
-        });
+        if should_run {
+            self.run_request = true;
+        }
+
+        let window_layer = window_response.response.layer_id;
+        if std::mem::take(&mut self.focus_request) {
+            ui.ctx().move_to_top(window_layer);
+        }
+        self.was_clicked = ui.input(|i| i.pointer.primary_pressed())
+            && pointer.is_some_and(|pos| ui.ctx().layer_id_at(pos) == Some(window_layer));
+
+        let response = &window_response.response;
+        if response.drag_started() {
+            self.drag_start_position = Some(response.rect.left_top() - response.drag_delta());
+        } else if !response.dragged() {
+            self.drag_start_position = None;
+        }
 
         self.is_collapsed = window_response.inner.is_none();
         self.position = window_response.response.rect.left_top();
+        self.size = window_response.response.rect.size();
 
         window_response.response
     }
@@ -505,16 +590,41 @@ fn render_editable_label(
     param: &mut FunctionParam,
     param_id: &u16,
     param_type: ParamType,
+    port_hit_size: f32,
 ) -> (Response, Response, Rect) {
     if !param.is_renaming {
         let row = ui.horizontal(|ui| {
-            let circle = ui.allocate_exact_size(vec2(5.0, 5.0), Sense::click());
+            let (circle_rect, _) = ui.allocate_exact_size(vec2(5.0, 5.0), Sense::hover());
+            // The port takes keyboard focus, so the label is left out of the focus order.
+            let port_response = ui.interact(
+                Rect::from_center_size(circle_rect.center(), Vec2::splat(port_hit_size)),
+                ui.id().with(("port", param_id)),
+                Sense::click_and_drag(),
+            );
+            port_response.widget_info(|| {
+                let kind = match param_type {
+                    ParamType::Input => "Input",
+                    ParamType::Output => "Output",
+                };
+                let value = param
+                    .last_value
+                    .as_ref()
+                    .map_or("no value".to_owned(), |value| format!("value {value}"));
+                WidgetInfo::labeled(
+                    WidgetType::Button,
+                    true,
+                    format!("{kind} {}, {value}", param.param_name),
+                )
+            });
             let label_response = ui.add(
                 Label::new(param.param_name.clone())
-                    .sense(Sense::click())
+                    .sense(Sense {
+                        focusable: false,
+                        ..Sense::click_and_drag()
+                    })
                     .wrap(),
             );
-            (label_response, circle.1, circle.0)
+            (label_response, port_response, circle_rect)
         });
         return row.inner;
     }
@@ -546,6 +656,7 @@ fn add_label_behavoir(
     param_rename_options: &mut Option<RenameOptions>,
     label_response: &egui::Response,
     param: &mut FunctionParam,
+    shortcuts: &WidgetShortcuts,
 ) {
     if label_response.hovered() && !param.is_renaming {
         ui.painter().rect(
@@ -569,12 +680,12 @@ fn add_label_behavoir(
 
     if param.is_renaming || param.is_editing {
         ui.input(|i| {
-            if i.key_pressed(Key::Escape) {
+            if keymap::is_pressed(i, shortcuts.cancel_edit) {
                 param.is_renaming = false;
                 param.is_editing = false;
                 *param_rename_options = None;
             }
-            if i.key_pressed(Key::Enter) {
+            if keymap::is_pressed(i, shortcuts.commit_edit) {
                 param.is_renaming = false;
                 param.is_editing = false;
             }
@@ -588,6 +699,7 @@ fn paint_last_value(
     circle_rect: Rect,
     edit_options: &mut Option<EditOptions>,
     param_type: ParamType,
+    shortcuts: &WidgetShortcuts,
 ) -> bool {
     let font_id = TextStyle::Body.resolve(ui.style());
     let visuals = ui.visuals();
@@ -622,11 +734,11 @@ fn paint_last_value(
                 })
                 .response;
 
-            if ui.input(|i| i.key_pressed(Key::Enter)) {
+            if ui.input(|i| keymap::is_pressed(i, shortcuts.commit_edit)) {
                 ui.memory_mut(|mem| mem.close_popup());
                 let parsed = constant_value.parse::<i64>();
                 if let Ok(value) = parsed {
-                    param.last_value = Some(Dynamic::from_int(value));
+                    param.set_last_value(Dynamic::from_int(value));
                 }
                 *edit_options = None;
 
@@ -673,6 +785,31 @@ fn paint_last_value(
     false
 }
 
+/// Gives keyboard focus to the first port after a focus request and
+/// remembers which port has it, drawing a focus ring around it.
+fn track_port_focus(
+    port_response: &Response,
+    param_id: u16,
+    should_focus_port: &mut bool,
+    focused_port: &mut Option<u16>,
+) {
+    if std::mem::take(should_focus_port) {
+        port_response.request_focus();
+    }
+    if port_response.has_focus() {
+        *focused_port = Some(param_id);
+        let visuals = port_response.ctx.style().visuals.clone();
+        port_response
+            .ctx
+            .layer_painter(port_response.layer_id)
+            .circle_stroke(
+                port_response.rect.center(),
+                port_response.rect.width().max(10.0) / 2.0 + 2.0,
+                visuals.selection.stroke,
+            );
+    }
+}
+
 fn paint_circle(ui: &Ui, circle_rect: &Rect) {
     ui.painter().circle(
         circle_rect.center(),
@@ -0,0 +1,335 @@
+use emath::{Pos2, Rect};
+use indexmap::IndexMap;
+use petgraph::algo::toposort;
+use petgraph::{stable_graph::StableGraph, Directed};
+use rhai::{Dynamic, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::runnable::{FunctionParam, LinkVertex, Runnable};
+use crate::testing::TestCase;
+use crate::trace::TraceEntry;
+
+/// The functions and links of a project without any editor state. This is what
+/// gets exported as project JSON and what runs outside of the editor.
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Graph {
+    pub functions: IndexMap<u16, Runnable>,
+    pub links: Vec<GraphLink>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+    #[serde(skip_serializing_if = "Layout::is_empty")]
+    pub layout: Layout,
+}
+
+/// Where the editor put things, so a project copied as JSON opens the way it
+/// was left. Running a project doesn't look at it.
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Layout {
+    /// Left top corner of each function.
+    pub positions: IndexMap<u16, Pos2>,
+    /// Links that go through reroute points.
+    pub reroutes: Vec<Reroute>,
+    pub comments: Vec<Comment>,
+    pub notes: Vec<Note>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Reroute {
+    pub start: LinkVertex,
+    pub end: LinkVertex,
+    /// In order from start to end.
+    pub points: Vec<Pos2>,
+}
+
+/// A titled frame drawn behind functions.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Comment {
+    pub title: String,
+    pub rect: Rect,
+    /// RGBA.
+    pub color: [u8; 4],
+}
+
+/// Free text on the canvas.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Note {
+    pub text: String,
+    pub pos: Pos2,
+}
+
+impl Layout {
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+            && self.reroutes.is_empty()
+            && self.comments.is_empty()
+            && self.notes.is_empty()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GraphLink {
+    pub start: LinkVertex,
+    pub end: LinkVertex,
+}
+
+/// An input nothing is linked to, or an output that isn't linked anywhere.
+/// Those are the values a host sets and reads.
+#[derive(Serialize, Clone, Debug)]
+pub struct Port {
+    /// `Function.Param`, how the host refers to the port. A function or
+    /// parameter whose name is shared gets its id appended, `Add [517].A`,
+    /// so that every key names one port.
+    pub key: String,
+    pub function_id: u16,
+    pub param_id: u16,
+    pub function: String,
+    pub name: String,
+    pub value: Option<Dynamic>,
+}
+
+/// Toposort of the functions over `edges`, which must already leave out the
+/// links into delays. Functions without links go last. `None` on a cycle.
+pub fn execution_order(
+    function_ids: impl Iterator<Item = u16>,
+    edges: impl Iterator<Item = (u16, u16)>,
+) -> Option<Vec<u16>> {
+    let function_ids: Vec<u16> = function_ids.collect();
+    let g: StableGraph<(), (), Directed, u32> =
+        StableGraph::from_edges(edges.map(|(from, to)| (from as u32, to as u32)));
+
+    let sorted = toposort(&g, None).ok()?;
+    let mut order: Vec<u16> = sorted
+        .into_iter()
+        .map(|node| node.index() as u16)
+        .filter(|function_id| function_ids.contains(function_id))
+        .collect();
+    for function_id in function_ids {
+        if !order.contains(&function_id) {
+            order.push(function_id);
+        }
+    }
+    Some(order)
+}
+
+/// Name of a parameter, with its id appended when a sibling has the same name.
+fn unique_name(params: &IndexMap<u16, FunctionParam>, param_id: u16) -> String {
+    let name = &params[&param_id].param_name;
+    let is_shared = params
+        .iter()
+        .any(|(id, param)| *id != param_id && param.param_name == *name);
+    if is_shared {
+        format!("{name} [{param_id}]")
+    } else {
+        name.clone()
+    }
+}
+
+impl Graph {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    fn is_delay(&self, function_id: u16) -> bool {
+        self.functions
+            .get(&function_id)
+            .is_some_and(|runnable| runnable.is_delay())
+    }
+
+    pub fn execution_order(&self) -> Option<Vec<u16>> {
+        execution_order(
+            self.functions.keys().copied(),
+            self.links
+                .iter()
+                .filter(|link| !self.is_delay(link.end.function_id))
+                .map(|link| (link.start.function_id, link.end.function_id)),
+        )
+    }
+
+    pub fn inputs(&self) -> Vec<Port> {
+        self.ports(|runnable| &runnable.inputs, |link| &link.end)
+    }
+
+    pub fn outputs(&self) -> Vec<Port> {
+        self.ports(|runnable| &runnable.outputs, |link| &link.start)
+    }
+
+    fn ports(
+        &self,
+        params: impl Fn(&Runnable) -> &IndexMap<u16, FunctionParam>,
+        linked_vertex: impl Fn(&GraphLink) -> &LinkVertex,
+    ) -> Vec<Port> {
+        self.functions
+            .iter()
+            .filter(|(_, runnable)| !runnable.is_delay())
+            .flat_map(|(function_id, runnable)| {
+                let params = params(runnable);
+                let function = self.port_function_name(*function_id);
+                params.iter().map(move |(param_id, param)| Port {
+                    key: format!("{function}.{}", unique_name(params, *param_id)),
+                    function_id: *function_id,
+                    param_id: *param_id,
+                    function: runnable.name.clone(),
+                    name: param.param_name.clone(),
+                    value: param.last_value.clone(),
+                })
+            })
+            .filter(|port| {
+                !self.links.iter().map(&linked_vertex).any(|vertex| {
+                    vertex.function_id == port.function_id && vertex.param_id == port.param_id
+                })
+            })
+            .collect()
+    }
+
+    /// The function part of port keys.
+    fn port_function_name(&self, function_id: u16) -> String {
+        let name = &self.functions[&function_id].name;
+        let is_shared = self
+            .functions
+            .iter()
+            .any(|(id, runnable)| *id != function_id && runnable.name == *name);
+        if is_shared {
+            format!("{name} [{function_id}]")
+        } else {
+            name.clone()
+        }
+    }
+
+    /// Sets the graph input called `key` (`Function.Param`).
+    pub fn set_input(&mut self, key: &str, value: Dynamic) -> Result<(), String> {
+        let port = self
+            .inputs()
+            .into_iter()
+            .find(|port| port.key == key)
+            .ok_or_else(|| format!("No graph input called {key}"))?;
+        let param = self
+            .functions
+            .get_mut(&port.function_id)
+            .and_then(|runnable| runnable.inputs.get_mut(&port.param_id))
+            .expect("Port was just listed");
+        param.set_last_value(value);
+        Ok(())
+    }
+
+    /// Runs the functions of `order` once and hands each run to `on_run`.
+    pub fn step_with(&mut self, engine: &Engine, order: &[u16], on_run: impl FnMut(TraceEntry)) {
+        self.run_functions(engine, order, on_run);
+        self.latch_delays();
+    }
+
+    /// Runs the functions of `order` one after another, without latching delays.
+    pub fn run_functions(
+        &mut self,
+        engine: &Engine,
+        order: &[u16],
+        mut on_run: impl FnMut(TraceEntry),
+    ) {
+        for function_id in order.iter().copied() {
+            let Some(runnable) = self.functions.get_mut(&function_id) else {
+                continue;
+            };
+            on_run(runnable.run_traced(engine, function_id));
+            self.propagate_outputs(function_id);
+        }
+    }
+
+    /// Stores the inputs of every delay for the next iteration.
+    pub fn latch_delays(&mut self) {
+        for runnable in self.functions.values_mut() {
+            if runnable.is_delay() {
+                runnable.latch_state();
+            }
+        }
+    }
+
+    /// Splits `order` into waves. Functions of a wave only depend on functions
+    /// of earlier waves, so they can run at the same time.
+    #[cfg(feature = "editor")]
+    pub fn waves(&self, order: &[u16]) -> Vec<Vec<u16>> {
+        let mut wave_of: IndexMap<u16, usize> = IndexMap::new();
+        for function_id in order.iter().copied() {
+            let wave = self
+                .links
+                .iter()
+                .filter(|link| link.end.function_id == function_id && !self.is_delay(function_id))
+                .filter_map(|link| wave_of.get(&link.start.function_id))
+                .map(|wave| wave + 1)
+                .max()
+                .unwrap_or(0);
+            wave_of.insert(function_id, wave);
+        }
+
+        let mut waves = vec![Vec::new(); wave_of.values().max().map_or(0, |wave| wave + 1)];
+        for (function_id, wave) in wave_of {
+            waves[wave].push(function_id);
+        }
+        waves
+    }
+
+    /// Hands the outputs of a function that just ran to the inputs linked to them.
+    pub fn propagate_outputs(&mut self, function_id: u16) {
+        for link in self.links.iter() {
+            if link.start.function_id != function_id {
+                continue;
+            }
+            let value = self
+                .functions
+                .get(&link.start.function_id)
+                .and_then(|runnable| runnable.outputs.get(&link.start.param_id))
+                .and_then(|param| param.last_value.clone());
+            let input = self
+                .functions
+                .get_mut(&link.end.function_id)
+                .and_then(|runnable| runnable.inputs.get_mut(&link.end.param_id));
+            if let (Some(value), Some(input)) = (value, input) {
+                input.update_last_value(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_function_names_get_distinct_port_keys() {
+        let mut graph = Graph::default();
+        for function_id in [1, 2] {
+            let runnable = Runnable::with_signature(
+                "Add".to_owned(),
+                "#{ Sum: A }".to_owned(),
+                &["A"],
+                &["Sum"],
+            );
+            graph.functions.insert(function_id, runnable);
+        }
+
+        let keys: Vec<String> = graph.inputs().into_iter().map(|port| port.key).collect();
+        assert_eq!(keys, ["Add [1].A", "Add [2].A"]);
+
+        graph.set_input("Add [2].A", "two".into()).unwrap();
+        assert!(graph.functions[&1].inputs[0].last_value.is_none());
+        assert!(graph.functions[&2].inputs[0].last_value.is_some());
+        assert!(graph.set_input("Add.A", 1.into()).is_err());
+    }
+
+    #[test]
+    fn layout_is_optional_in_project_json() {
+        let graph: Graph = serde_json::from_str(r#"{ "functions": {}, "links": [] }"#).unwrap();
+        assert!(graph.layout.is_empty());
+        assert!(!graph.to_json().contains("layout"));
+
+        let mut graph = Graph::default();
+        graph.layout.positions.insert(1, Pos2::new(10.0, 20.0));
+        graph.layout.notes.push(Note {
+            text: "Inputs come from the form".to_owned(),
+            pos: Pos2::new(0.0, -40.0),
+        });
+        let graph: Graph = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(graph.layout.positions[&1], Pos2::new(10.0, 20.0));
+        assert_eq!(graph.layout.notes[0].text, "Inputs come from the form");
+    }
+}
@@ -0,0 +1,91 @@
+use egui::{Pos2, Rect, Stroke, Ui};
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+const SPACING_RANGE: RangeInclusive<f32> = 5.0..=200.0;
+
+/// Background grid of the canvas that dragged windows can snap to.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Grid {
+    pub is_visible: bool,
+    pub snap: bool,
+    pub spacing: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            is_visible: false,
+            snap: false,
+            spacing: 20.0,
+        }
+    }
+}
+
+impl Grid {
+    /// The step kept inside the range the settings allow, whatever a restored
+    /// state says. A zero or negative step would never finish painting.
+    fn spacing(&self) -> f32 {
+        self.spacing
+            .max(*SPACING_RANGE.start())
+            .min(*SPACING_RANGE.end())
+    }
+
+    pub fn snap(&self, pos: Pos2) -> Pos2 {
+        let spacing = self.spacing();
+        Pos2 {
+            x: (pos.x / spacing).round() * spacing,
+            y: (pos.y / spacing).round() * spacing,
+        }
+    }
+
+    pub fn paint(&self, ui: &Ui, rect: Rect) {
+        if !self.is_visible {
+            return;
+        }
+        let painter = ui.painter_at(rect);
+        let stroke = Stroke::new(1.0_f32, ui.visuals().widgets.noninteractive.bg_stroke.color);
+        let spacing = self.spacing();
+
+        let mut x = (rect.left() / spacing).ceil() * spacing;
+        while x <= rect.right() {
+            painter.vline(x, rect.y_range(), stroke);
+            x += spacing;
+        }
+        let mut y = (rect.top() / spacing).ceil() * spacing;
+        while y <= rect.bottom() {
+            painter.hline(rect.x_range(), y, stroke);
+            y += spacing;
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.is_visible, "Show grid");
+        ui.checkbox(&mut self.snap, "Snap to grid");
+        ui.add(
+            egui::DragValue::new(&mut self.spacing)
+                .range(SPACING_RANGE)
+                .prefix("Step: "),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restored_spacing_outside_the_range_still_snaps() {
+        for spacing in [0.0, -20.0, f32::NAN] {
+            let grid = Grid {
+                is_visible: true,
+                snap: true,
+                spacing,
+            };
+            let snapped = grid.snap(Pos2::new(12.0, 23.0));
+            assert_eq!(snapped, Pos2::new(10.0, 25.0));
+        }
+    }
+}
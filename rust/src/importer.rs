@@ -0,0 +1,57 @@
+use egui::{Color32, RichText, ScrollArea, TextEdit, Window};
+
+use crate::script::{self, Imported};
+
+/// Window to paste a rhai script into, turned into functions on "Import".
+#[derive(Default, Debug)]
+pub struct ScriptImporter {
+    pub is_open: bool,
+    source: String,
+    /// Why the last import failed.
+    error: Option<String>,
+}
+
+impl ScriptImporter {
+    /// The pasted script once "Import" is clicked and it parses.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Imported> {
+        let mut imported = None;
+        let mut is_open = self.is_open;
+        Window::new("Import script")
+            .open(&mut is_open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(
+                        "Every fn becomes a function, calls in the main section become links. \
+                         A .rhai file dropped on the window is imported too.",
+                    )
+                    .weak()
+                    .small(),
+                );
+                ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    ui.add(
+                        TextEdit::multiline(&mut self.source)
+                            .code_editor()
+                            .desired_rows(12)
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+                if let Some(ref error) = self.error {
+                    ui.label(RichText::new(format!("⚠ {error}")).color(Color32::YELLOW));
+                }
+                if ui.button("Import").clicked() {
+                    match script::import(&self.source) {
+                        Ok(script) => {
+                            imported = Some(script);
+                            self.error = None;
+                            self.source.clear();
+                            self.is_open = false;
+                        }
+                        Err(error) => self.error = Some(error),
+                    }
+                }
+            });
+        self.is_open &= is_open;
+        imported
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+
+use egui::{CollapsingHeader, Color32, Id, RichText, ScrollArea, Ui};
+use indexmap::IndexMap;
+use rhai::{Array, Dynamic, Map};
+
+use crate::function_widget::FunctionWidget;
+use crate::runnable::{FunctionParam, LinkVertex};
+
+/// Side panel showing the value of one parameter as a tree, its history
+/// and what changed compared to an earlier value.
+#[derive(Default, Debug)]
+pub struct ValueInspector {
+    pub target: Option<LinkVertex>,
+    /// Index into the history of the value the current one is compared with.
+    compare_index: usize,
+}
+
+impl ValueInspector {
+    pub fn inspect(&mut self, target: LinkVertex) {
+        self.target = Some(target);
+        self.compare_index = 0;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, functions: &IndexMap<u16, FunctionWidget>) {
+        let Some(target) = self.target.clone() else {
+            return;
+        };
+        let Some(widget) = functions.get(&target.function_id) else {
+            self.target = None;
+            return;
+        };
+        let param = widget
+            .runnable
+            .inputs
+            .get(&target.param_id)
+            .or_else(|| widget.runnable.outputs.get(&target.param_id));
+        let Some(param) = param else {
+            self.target = None;
+            return;
+        };
+
+        egui::SidePanel::right("value_inspector")
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("{}.{}", widget.runnable.name, param.param_name));
+                    if ui.small_button("❌").clicked() {
+                        self.target = None;
+                    }
+                });
+                ui.separator();
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    ui.label(RichText::new("Current").strong());
+                    match param.last_value {
+                        Some(ref value) => {
+                            value_tree(ui, Id::new("inspector_current"), "value", value)
+                        }
+                        None => {
+                            ui.label("<none>");
+                        }
+                    }
+
+                    ui.separator();
+                    self.history_ui(ui, param);
+
+                    ui.separator();
+                    self.diff_ui(ui, param);
+                });
+            });
+    }
+
+    fn history_ui(&mut self, ui: &mut Ui, param: &FunctionParam) {
+        ui.label(RichText::new(format!("History ({})", param.history.len())).strong());
+        for (index, value) in param.history.iter().rev().enumerate() {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.compare_index, index, "")
+                    .on_hover_text("Compare with the current value");
+                value_tree(
+                    ui,
+                    Id::new(("inspector_history", index)),
+                    &format!("-{}", index + 1),
+                    value,
+                );
+            });
+        }
+    }
+
+    fn diff_ui(&self, ui: &mut Ui, param: &FunctionParam) {
+        ui.label(RichText::new("Diff").strong());
+        let previous = param.history.iter().rev().nth(self.compare_index);
+        let (Some(current), Some(previous)) = (param.last_value.as_ref(), previous) else {
+            ui.label("Nothing to compare yet");
+            return;
+        };
+
+        let changes = diff_values(previous, current);
+        if changes.is_empty() {
+            ui.label("No changes");
+        }
+        for change in changes {
+            let (text, color) = match change {
+                ValueChange::Added(path, value) => (format!("+ {path}: {value}"), Color32::GREEN),
+                ValueChange::Removed(path, value) => (format!("- {path}: {value}"), Color32::RED),
+                ValueChange::Changed(path, old, new) => {
+                    (format!("~ {path}: {old} → {new}"), Color32::YELLOW)
+                }
+            };
+            ui.label(RichText::new(text).color(color).monospace());
+        }
+    }
+}
+
+/// Renders maps and arrays as collapsible nodes and everything else as a labelled leaf.
+pub fn value_tree(ui: &mut Ui, id: Id, label: &str, value: &Dynamic) {
+    if value.is_map() {
+        let map = value.clone().cast::<Map>();
+        CollapsingHeader::new(format!("{label}: map[{}]", map.len()))
+            .id_salt(id)
+            .default_open(true)
+            .show(ui, |ui| {
+                for (key, item) in map.iter() {
+                    value_tree(ui, id.with(key.as_str()), key, item);
+                }
+            });
+    } else if value.is_array() {
+        let array = value.clone().cast::<Array>();
+        CollapsingHeader::new(format!("{label}: array[{}]", array.len()))
+            .id_salt(id)
+            .default_open(true)
+            .show(ui, |ui| {
+                for (index, item) in array.iter().enumerate() {
+                    value_tree(ui, id.with(index), &index.to_string(), item);
+                }
+            });
+    } else {
+        ui.label(format!("{label}: {value} ({})", value.type_name()));
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ValueChange {
+    Added(String, String),
+    Removed(String, String),
+    Changed(String, String, String),
+}
+
+/// Compares the leaves of two values by their path inside nested maps and arrays.
+pub fn diff_values(old: &Dynamic, new: &Dynamic) -> Vec<ValueChange> {
+    let mut old_leaves = BTreeMap::new();
+    let mut new_leaves = BTreeMap::new();
+    flatten("$", old, &mut old_leaves);
+    flatten("$", new, &mut new_leaves);
+
+    let mut changes = Vec::new();
+    for (path, old_value) in old_leaves.iter() {
+        match new_leaves.get(path) {
+            None => changes.push(ValueChange::Removed(path.clone(), old_value.clone())),
+            Some(new_value) if new_value != old_value => changes.push(ValueChange::Changed(
+                path.clone(),
+                old_value.clone(),
+                new_value.clone(),
+            )),
+            Some(_) => {}
+        }
+    }
+    for (path, new_value) in new_leaves {
+        if !old_leaves.contains_key(&path) {
+            changes.push(ValueChange::Added(path, new_value));
+        }
+    }
+    changes
+}
+
+fn flatten(path: &str, value: &Dynamic, leaves: &mut BTreeMap<String, String>) {
+    if value.is_map() {
+        for (key, item) in value.clone().cast::<Map>().iter() {
+            flatten(&format!("{path}.{key}"), item, leaves);
+        }
+    } else if value.is_array() {
+        for (index, item) in value.clone().cast::<Array>().iter().enumerate() {
+            flatten(&format!("{path}[{index}]"), item, leaves);
+        }
+    } else {
+        leaves.insert(path.to_owned(), format!("{value} ({})", value.type_name()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::Engine;
+
+    use super::*;
+
+    #[test]
+    fn changes_are_listed_by_path() {
+        let engine = Engine::new();
+        let old: Dynamic = engine
+            .eval(r#"#{ a: 1, b: [1, 2], c: #{ d: "x" } }"#)
+            .unwrap();
+        let new: Dynamic = engine.eval("#{ a: 1, b: [1, 3, 4], c: #{} }").unwrap();
+
+        assert_eq!(
+            diff_values(&old, &new),
+            [
+                ValueChange::Changed("$.b[1]".into(), "2 (i64)".into(), "3 (i64)".into()),
+                ValueChange::Removed("$.c.d".into(), "x (string)".into()),
+                ValueChange::Added("$.b[2]".into(), "4 (i64)".into()),
+            ]
+        );
+        assert!(diff_values(&old, &old).is_empty());
+    }
+}
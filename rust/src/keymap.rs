@@ -0,0 +1,381 @@
+use egui::{Color32, Event, Key, KeyboardShortcut, Modifiers, RichText, Window};
+use serde::{Deserialize, Serialize};
+
+use crate::palette::Command;
+
+/// Where a shortcut is listened to. Shortcuts only conflict inside one scope.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scope {
+    Canvas,
+    /// While a parameter name or code is being edited.
+    TextEditing,
+}
+
+impl Scope {
+    fn name(&self) -> &'static str {
+        match self {
+            Scope::Canvas => "Canvas",
+            Scope::TextEditing => "Text editing",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Command(Command),
+    OpenSearch,
+    OpenCommands,
+    NextNode,
+    PreviousNode,
+    /// Switches the hovered or selected nodes between signature and code.
+    ToggleWidgetMode,
+    CancelLink,
+    CommitEdit,
+    CancelEdit,
+    TriggerCompletion,
+}
+
+impl Action {
+    pub fn all() -> impl Iterator<Item = Action> {
+        [
+            Action::OpenSearch,
+            Action::OpenCommands,
+            Action::NextNode,
+            Action::PreviousNode,
+            Action::ToggleWidgetMode,
+            Action::CancelLink,
+        ]
+        .into_iter()
+        .chain(Command::ALL.into_iter().map(Action::Command))
+        .chain([
+            Action::CommitEdit,
+            Action::CancelEdit,
+            Action::TriggerCompletion,
+        ])
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Command(command) => command.name(),
+            Action::OpenSearch => "Search nodes, templates and commands",
+            Action::OpenCommands => "Search commands",
+            Action::NextNode => "Focus next node",
+            Action::PreviousNode => "Focus previous node",
+            Action::ToggleWidgetMode => "Toggle signature / code",
+            Action::CancelLink => "Cancel link",
+            Action::CommitEdit => "Confirm name or value",
+            Action::CancelEdit => "Cancel editing",
+            Action::TriggerCompletion => "Show completions",
+        }
+    }
+
+    pub fn scope(&self) -> Scope {
+        match self {
+            Action::CommitEdit | Action::CancelEdit | Action::TriggerCompletion => {
+                Scope::TextEditing
+            }
+            _ => Scope::Canvas,
+        }
+    }
+
+    pub fn default_shortcut(&self) -> Option<KeyboardShortcut> {
+        let shortcut = |modifiers, key| Some(KeyboardShortcut::new(modifiers, key));
+        match self {
+            Action::OpenSearch => shortcut(Modifiers::COMMAND, Key::P),
+            Action::OpenCommands => shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::P),
+            Action::NextNode => shortcut(Modifiers::NONE, Key::F6),
+            Action::PreviousNode => shortcut(Modifiers::SHIFT, Key::F6),
+            Action::ToggleWidgetMode => shortcut(Modifiers::CTRL, Key::Q),
+            Action::CancelLink => shortcut(Modifiers::NONE, Key::Escape),
+            Action::CommitEdit => shortcut(Modifiers::NONE, Key::Enter),
+            Action::CancelEdit => shortcut(Modifiers::NONE, Key::Escape),
+            Action::TriggerCompletion => shortcut(Modifiers::CTRL, Key::Space),
+            Action::Command(Command::RunAll) => shortcut(Modifiers::COMMAND, Key::Enter),
+            Action::Command(Command::AutoLayout) => {
+                shortcut(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::L)
+            }
+            Action::Command(Command::StartDebugging) => shortcut(Modifiers::NONE, Key::F9),
+            Action::Command(Command::DebugStep) => shortcut(Modifiers::NONE, Key::F10),
+            Action::Command(Command::DebugContinue) => shortcut(Modifiers::NONE, Key::F8),
+            Action::Command(Command::StopDebugging) => shortcut(Modifiers::SHIFT, Key::F8),
+            Action::Command(Command::Save) => shortcut(Modifiers::COMMAND, Key::S),
+            Action::Command(_) => None,
+        }
+    }
+}
+
+/// Shortcuts a function widget listens to, handed to it every frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WidgetShortcuts {
+    pub commit_edit: Option<KeyboardShortcut>,
+    pub cancel_edit: Option<KeyboardShortcut>,
+    pub trigger_completion: Option<KeyboardShortcut>,
+}
+
+/// Bindings of every action. Only the ones changed by the user are stored,
+/// the rest fall back to their defaults.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Keymap {
+    /// A list rather than a map, JSON objects only take string keys.
+    overrides: Vec<(Action, Option<KeyboardShortcut>)>,
+    #[serde(skip)]
+    pub is_open: bool,
+    /// Action waiting for the user to press its new shortcut.
+    #[serde(skip)]
+    capturing: Option<Action>,
+}
+
+impl Keymap {
+    pub fn shortcut(&self, action: Action) -> Option<KeyboardShortcut> {
+        match self.overrides.iter().find(|(other, _)| *other == action) {
+            Some((_, shortcut)) => *shortcut,
+            None => action.default_shortcut(),
+        }
+    }
+
+    pub fn set_shortcut(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) {
+        self.reset(action);
+        if shortcut != action.default_shortcut() {
+            self.overrides.push((action, shortcut));
+        }
+    }
+
+    fn is_overridden(&self, action: Action) -> bool {
+        self.overrides.iter().any(|(other, _)| *other == action)
+    }
+
+    fn reset(&mut self, action: Action) {
+        self.overrides.retain(|(other, _)| *other != action);
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    pub fn widget_shortcuts(&self) -> WidgetShortcuts {
+        WidgetShortcuts {
+            commit_edit: self.shortcut(Action::CommitEdit),
+            cancel_edit: self.shortcut(Action::CancelEdit),
+            trigger_completion: self.shortcut(Action::TriggerCompletion),
+        }
+    }
+
+    /// Text of the shortcut bound to `action`, empty when unbound.
+    pub fn format(&self, ctx: &egui::Context, action: Action) -> String {
+        self.shortcut(action)
+            .map(|shortcut| ctx.format_shortcut(&shortcut))
+            .unwrap_or_default()
+    }
+
+    /// Other actions of the same scope bound to the same shortcut as `action`.
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let Some(shortcut) = self.shortcut(action) else {
+            return Vec::new();
+        };
+        Action::all()
+            .filter(|other| {
+                *other != action
+                    && other.scope() == action.scope()
+                    && self.shortcut(*other) == Some(shortcut)
+            })
+            .collect()
+    }
+
+    /// Canvas actions whose shortcut was pressed this frame. The shortcuts are
+    /// consumed, more specific ones first so Ctrl+Shift+P doesn't also trigger Ctrl+P.
+    /// Cancel link is only listened to while a link is being made, so its key
+    /// is left to others the rest of the time.
+    pub fn pressed_actions(&self, ctx: &egui::Context, is_linking: bool) -> Vec<Action> {
+        if self.is_capturing() {
+            return Vec::new();
+        }
+        let is_typing = ctx.wants_keyboard_input();
+        let mut bindings: Vec<(Action, KeyboardShortcut)> = Action::all()
+            .filter(|action| action.scope() == Scope::Canvas)
+            .filter(|action| is_linking || *action != Action::CancelLink)
+            .filter_map(|action| Some((action, self.shortcut(action)?)))
+            .filter(|(_, shortcut)| !is_typing || works_while_typing(shortcut))
+            .collect();
+        bindings.sort_by_key(|(_, shortcut)| std::cmp::Reverse(modifier_count(shortcut.modifiers)));
+
+        ctx.input_mut(|i| {
+            bindings
+                .into_iter()
+                .filter(|(_, shortcut)| i.consume_shortcut(shortcut))
+                .map(|(action, _)| action)
+                .collect()
+        })
+    }
+
+    /// Settings window listing every action with its shortcut.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if let Some(action) = self.capturing {
+            let pressed = ctx.input_mut(|i| {
+                let pressed = i.events.iter().find_map(|event| match event {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(KeyboardShortcut::new(*modifiers, *key)),
+                    _ => None,
+                });
+                if let Some(shortcut) = pressed {
+                    i.consume_shortcut(&shortcut);
+                }
+                pressed
+            });
+            match pressed {
+                Some(shortcut)
+                    if shortcut.logical_key == Key::Escape && shortcut.modifiers.is_none() =>
+                {
+                    self.capturing = None;
+                }
+                Some(shortcut) => {
+                    self.set_shortcut(action, Some(shortcut));
+                    self.capturing = None;
+                }
+                None => {}
+            }
+        }
+
+        let mut is_open = self.is_open;
+        Window::new("Keyboard shortcuts")
+            .open(&mut is_open)
+            .vscroll(true)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                let conflict_count = Action::all()
+                    .filter(|action| !self.conflicts(*action).is_empty())
+                    .count();
+                if conflict_count > 0 {
+                    ui.colored_label(
+                        Color32::from_rgb(220, 80, 80),
+                        format!("{conflict_count} actions share a shortcut"),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    ui.weak("Click a shortcut and press the new keys, Esc to keep it.");
+                    if ui.button("Reset all").clicked() {
+                        self.overrides.clear();
+                    }
+                });
+
+                for scope in [Scope::Canvas, Scope::TextEditing] {
+                    ui.separator();
+                    ui.strong(scope.name());
+                    egui::Grid::new(("keymap", scope.name()))
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for action in Action::all().filter(|action| action.scope() == scope) {
+                                self.binding_row(ui, action);
+                                ui.end_row();
+                            }
+                        });
+                }
+            });
+        self.is_open = is_open;
+        if !self.is_open {
+            self.capturing = None;
+        }
+    }
+
+    fn binding_row(&mut self, ui: &mut egui::Ui, action: Action) {
+        ui.label(action.name());
+
+        let conflicts = self.conflicts(action);
+        let text = if self.capturing == Some(action) {
+            RichText::new("Press keys…").italics()
+        } else {
+            match self.shortcut(action) {
+                Some(shortcut) => RichText::new(ui.ctx().format_shortcut(&shortcut)),
+                None => RichText::new("—").weak(),
+            }
+        };
+        let text = if conflicts.is_empty() {
+            text
+        } else {
+            text.color(Color32::from_rgb(220, 80, 80))
+        };
+        let mut response = ui.button(text);
+        if !conflicts.is_empty() {
+            let names: Vec<&str> = conflicts.iter().map(|other| other.name()).collect();
+            response = response.on_hover_text(format!("Also bound to: {}", names.join(", ")));
+        }
+        if response.clicked() {
+            self.capturing = Some(action);
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.shortcut(action).is_some(), egui::Button::new("✖"))
+                .on_hover_text("Unbind")
+                .clicked()
+            {
+                self.set_shortcut(action, None);
+            }
+            if ui
+                .add_enabled(self.is_overridden(action), egui::Button::new("↺"))
+                .on_hover_text("Reset to default")
+                .clicked()
+            {
+                self.reset(action);
+            }
+        });
+    }
+}
+
+/// Whether `shortcut` was pressed this frame, without consuming it.
+pub fn is_pressed(input: &egui::InputState, shortcut: Option<KeyboardShortcut>) -> bool {
+    shortcut.is_some_and(|shortcut| {
+        input.modifiers.matches_logically(shortcut.modifiers)
+            && input.key_pressed(shortcut.logical_key)
+    })
+}
+
+/// Shortcuts that can't be typed into a text field, so they still work while one has focus.
+fn works_while_typing(shortcut: &KeyboardShortcut) -> bool {
+    let modifiers = shortcut.modifiers;
+    let name = shortcut.logical_key.name();
+    let is_function_key = name.starts_with('F') && name[1..].parse::<u8>().is_ok();
+    modifiers.command || modifiers.ctrl || modifiers.alt || is_function_key
+}
+
+fn modifier_count(modifiers: Modifiers) -> usize {
+    [
+        modifiers.alt,
+        modifiers.ctrl || modifiers.command,
+        modifiers.shift,
+    ]
+    .iter()
+    .filter(|pressed| **pressed)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_survive_a_json_round_trip() {
+        let mut keymap = Keymap::default();
+        let save = Action::Command(Command::Save);
+        keymap.set_shortcut(save, Some(KeyboardShortcut::new(Modifiers::ALT, Key::S)));
+        keymap.set_shortcut(Action::CancelLink, None);
+
+        let json = serde_json::to_string(&keymap).unwrap();
+        let keymap: Keymap = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            keymap.shortcut(save),
+            Some(KeyboardShortcut::new(Modifiers::ALT, Key::S))
+        );
+        assert_eq!(keymap.shortcut(Action::CancelLink), None);
+        assert_eq!(
+            keymap.shortcut(Action::OpenSearch),
+            Action::OpenSearch.default_shortcut()
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "editor")]
+use emath::Rect;
+use emath::{Pos2, Vec2};
+
+use crate::graph::execution_order;
+
+const COLUMN_GAP: f32 = 80.0;
+const ROW_GAP: f32 = 30.0;
+const CROSSING_SWEEPS: usize = 8;
+
+/// Layered (Sugiyama-style) layout: nodes go to columns by their depth in the
+/// graph, rows inside columns are ordered by the barycenter of their neighbours
+/// to reduce link crossings.
+pub fn layered_layout(
+    nodes: &[(u16, Vec2)],
+    edges: &[(u16, u16)],
+    origin: Pos2,
+) -> HashMap<u16, Pos2> {
+    let sizes: HashMap<u16, Vec2> = nodes.iter().copied().collect();
+    let edges: Vec<(u16, u16)> = edges
+        .iter()
+        .copied()
+        .filter(|(from, to)| from != to && sizes.contains_key(from) && sizes.contains_key(to))
+        .collect();
+
+    let mut layers = assign_layers(nodes, &edges);
+    reduce_crossings(&mut layers, &edges);
+
+    let mut positions = HashMap::new();
+    let mut x = origin.x;
+    for layer in layers.iter() {
+        let mut y = origin.y;
+        let mut column_width: f32 = 0.0;
+        for id in layer {
+            let size = sizes[id];
+            positions.insert(*id, Pos2 { x, y });
+            y += size.y + ROW_GAP;
+            column_width = column_width.max(size.x);
+        }
+        x += column_width + COLUMN_GAP;
+    }
+    positions
+}
+
+/// Longest path from the sources, following the execution order. When links
+/// form a cycle, the nodes keep their order and links back to an earlier node
+/// are left out.
+fn assign_layers(nodes: &[(u16, Vec2)], edges: &[(u16, u16)]) -> Vec<Vec<u16>> {
+    let ids = || nodes.iter().map(|(id, _)| *id);
+    let order = execution_order(ids(), edges.iter().copied()).unwrap_or_else(|| ids().collect());
+
+    let mut sources: HashMap<u16, Vec<u16>> = HashMap::new();
+    for (from, to) in edges {
+        sources.entry(*to).or_default().push(*from);
+    }
+    let mut depth: HashMap<u16, usize> = HashMap::new();
+    for id in order {
+        let node_depth = sources
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .filter_map(|from| depth.get(from))
+            .map(|from_depth| from_depth + 1)
+            .max()
+            .unwrap_or(0);
+        depth.insert(id, node_depth);
+    }
+
+    let layer_count = depth.values().max().map_or(1, |max| max + 1);
+    let mut layers = vec![Vec::new(); layer_count];
+    for (id, _) in nodes {
+        layers[depth.get(id).copied().unwrap_or(0)].push(*id);
+    }
+    layers
+}
+
+fn reduce_crossings(layers: &mut [Vec<u16>], edges: &[(u16, u16)]) {
+    for sweep in 0..CROSSING_SWEEPS {
+        if sweep % 2 == 0 {
+            for index in 1..layers.len() {
+                let (fixed, free) = layers.split_at_mut(index);
+                order_by_barycenter(&mut free[0], &fixed[index - 1], edges, false);
+            }
+        } else {
+            for index in (0..layers.len().saturating_sub(1)).rev() {
+                let (free, fixed) = layers.split_at_mut(index + 1);
+                order_by_barycenter(&mut free[index], &fixed[0], edges, true);
+            }
+        }
+    }
+}
+
+/// Sorts `layer` by the mean row of its neighbours in `fixed`. With `outgoing`
+/// the neighbours are the targets of links, otherwise their sources.
+fn order_by_barycenter(layer: &mut [u16], fixed: &[u16], edges: &[(u16, u16)], outgoing: bool) {
+    let rows: HashMap<u16, usize> = fixed
+        .iter()
+        .enumerate()
+        .map(|(row, id)| (*id, row))
+        .collect();
+
+    let barycenters: HashMap<u16, f32> = layer
+        .iter()
+        .enumerate()
+        .map(|(current_row, id)| {
+            let neighbour_rows: Vec<usize> = edges
+                .iter()
+                .filter_map(|(from, to)| match outgoing {
+                    true if from == id => rows.get(to).copied(),
+                    false if to == id => rows.get(from).copied(),
+                    _ => None,
+                })
+                .collect();
+            let barycenter = if neighbour_rows.is_empty() {
+                current_row as f32
+            } else {
+                neighbour_rows.iter().sum::<usize>() as f32 / neighbour_rows.len() as f32
+            };
+            (*id, barycenter)
+        })
+        .collect();
+
+    layer.sort_by(|a, b| barycenters[a].total_cmp(&barycenters[b]));
+}
+
+#[cfg(feature = "editor")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alignment {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Same horizontal center.
+    CenterX,
+    /// Same vertical center.
+    CenterY,
+}
+
+/// New left top corners that line the rectangles up along one edge or center.
+#[cfg(feature = "editor")]
+pub fn align(rects: &[(u16, Rect)], alignment: Alignment) -> HashMap<u16, Pos2> {
+    let Some(bounds) = rects
+        .iter()
+        .map(|(_, rect)| *rect)
+        .reduce(|a, b| a.union(b))
+    else {
+        return HashMap::new();
+    };
+
+    rects
+        .iter()
+        .map(|(id, rect)| {
+            let pos = match alignment {
+                Alignment::Left => Pos2::new(bounds.left(), rect.top()),
+                Alignment::Right => Pos2::new(bounds.right() - rect.width(), rect.top()),
+                Alignment::Top => Pos2::new(rect.left(), bounds.top()),
+                Alignment::Bottom => Pos2::new(rect.left(), bounds.bottom() - rect.height()),
+                Alignment::CenterX => Pos2::new(bounds.center().x - rect.width() / 2.0, rect.top()),
+                Alignment::CenterY => {
+                    Pos2::new(rect.left(), bounds.center().y - rect.height() / 2.0)
+                }
+            };
+            (*id, pos)
+        })
+        .collect()
+}
+
+/// New left top corners that keep the outermost rectangles in place and make
+/// the gaps between neighbours equal along the chosen axis.
+#[cfg(feature = "editor")]
+pub fn distribute(rects: &[(u16, Rect)], horizontally: bool) -> HashMap<u16, Pos2> {
+    let mut sorted = rects.to_vec();
+    let start = |rect: &Rect| {
+        if horizontally {
+            rect.left()
+        } else {
+            rect.top()
+        }
+    };
+    let length = |rect: &Rect| {
+        if horizontally {
+            rect.width()
+        } else {
+            rect.height()
+        }
+    };
+    sorted.sort_by(|(_, a), (_, b)| start(a).total_cmp(&start(b)));
+
+    let (Some((_, first)), Some((_, last))) = (sorted.first(), sorted.last()) else {
+        return HashMap::new();
+    };
+    let span = start(last) + length(last) - start(first);
+    let occupied: f32 = sorted.iter().map(|(_, rect)| length(rect)).sum();
+    let gap = (span - occupied) / (sorted.len().max(2) - 1) as f32;
+
+    let mut cursor = start(first);
+    sorted
+        .iter()
+        .map(|(id, rect)| {
+            let pos = if horizontally {
+                Pos2::new(cursor, rect.top())
+            } else {
+                Pos2::new(rect.left(), cursor)
+            };
+            cursor += length(rect) + gap;
+            (*id, pos)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Vec2 = Vec2 { x: 100.0, y: 50.0 };
+
+    fn nodes(ids: &[u16]) -> Vec<(u16, Vec2)> {
+        ids.iter().map(|id| (*id, SIZE)).collect()
+    }
+
+    #[test]
+    fn nodes_go_to_columns_by_their_longest_path() {
+        let positions =
+            layered_layout(&nodes(&[1, 2, 3, 4]), &[(1, 2), (2, 3), (1, 3)], Pos2::ZERO);
+
+        let column = |id: u16| positions[&id].x / (SIZE.x + COLUMN_GAP);
+        assert_eq!(column(1), 0.0);
+        assert_eq!(column(2), 1.0);
+        assert_eq!(column(3), 2.0);
+        assert_eq!(column(4), 0.0);
+        assert_ne!(positions[&1].y, positions[&4].y);
+    }
+
+    #[test]
+    fn rows_follow_their_sources_to_avoid_crossings() {
+        let positions = layered_layout(&nodes(&[1, 2, 3, 4]), &[(1, 4), (2, 3)], Pos2::ZERO);
+
+        assert!(positions[&1].y < positions[&2].y);
+        assert!(positions[&4].y < positions[&3].y);
+    }
+
+    #[test]
+    fn cycles_still_get_laid_out() {
+        let positions = layered_layout(&nodes(&[1, 2]), &[(1, 2), (2, 1)], Pos2::ZERO);
+
+        assert_eq!(positions[&1].x, 0.0);
+        assert_eq!(positions[&2].x, SIZE.x + COLUMN_GAP);
+    }
+}
@@ -1,5 +1,58 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(feature = "core")]
+mod diagram;
+#[cfg(feature = "core")]
+pub mod embed;
+#[cfg(feature = "core")]
+mod graph;
+#[cfg(feature = "core")]
+mod layout;
+#[cfg(feature = "core")]
+mod runnable;
+#[cfg(feature = "core")]
+mod script;
+#[cfg(feature = "core")]
+mod testing;
+#[cfg(feature = "core")]
+mod trace;
+#[cfg(all(feature = "core", target_arch = "wasm32"))]
+mod web_api;
+
+#[cfg(feature = "editor")]
 mod app;
+#[cfg(feature = "editor")]
+mod comments;
+#[cfg(feature = "editor")]
+mod debugger;
+#[cfg(feature = "editor")]
+mod diagnostics;
+#[cfg(feature = "editor")]
+mod events;
+#[cfg(feature = "editor")]
+mod executor;
+#[cfg(feature = "editor")]
+mod files;
+#[cfg(feature = "editor")]
+mod flow;
+#[cfg(feature = "editor")]
 mod function_widget;
+#[cfg(feature = "editor")]
+mod grid;
+#[cfg(feature = "editor")]
+mod importer;
+#[cfg(feature = "editor")]
+mod inspector;
+#[cfg(feature = "editor")]
+mod keymap;
+#[cfg(feature = "editor")]
+mod library;
+#[cfg(feature = "editor")]
+mod palette;
+#[cfg(all(feature = "editor", not(target_arch = "wasm32")))]
+mod pool;
+#[cfg(feature = "editor")]
+mod routing;
+
+#[cfg(feature = "editor")]
 pub use app::TemplateApp;
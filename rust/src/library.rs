@@ -0,0 +1,80 @@
+use crate::runnable::Runnable;
+
+/// A ready-made function that can be inserted on the canvas.
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub inputs: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+    pub code: &'static str,
+}
+
+impl Template {
+    pub fn runnable(&self, name: String) -> Runnable {
+        Runnable::with_signature(name, self.code.to_owned(), self.inputs, self.outputs)
+    }
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "Add",
+        description: "Sum of two values",
+        inputs: &["A", "B"],
+        outputs: &["Sum"],
+        code: "#{Sum: A + B}",
+    },
+    Template {
+        name: "Subtract",
+        description: "Difference of two values",
+        inputs: &["A", "B"],
+        outputs: &["Difference"],
+        code: "#{Difference: A - B}",
+    },
+    Template {
+        name: "Multiply",
+        description: "Product of two values",
+        inputs: &["A", "B"],
+        outputs: &["Product"],
+        code: "#{Product: A * B}",
+    },
+    Template {
+        name: "Divide",
+        description: "Quotient and remainder, zero when dividing by zero",
+        inputs: &["A", "B"],
+        outputs: &["Quotient", "Remainder"],
+        code: r#"if B == 0 {
+    #{Quotient: 0, Remainder: 0}
+} else {
+    #{Quotient: A / B, Remainder: A % B}
+}"#,
+    },
+    Template {
+        name: "Min / Max",
+        description: "Smaller and larger of two values",
+        inputs: &["A", "B"],
+        outputs: &["Min", "Max"],
+        code: "if A < B { #{Min: A, Max: B} } else { #{Min: B, Max: A} }",
+    },
+    Template {
+        name: "Clamp",
+        description: "Keeps a value between two bounds",
+        inputs: &["Value", "Low", "High"],
+        outputs: &["Clamped"],
+        code: r#"let clamped = if Value < Low { Low } else if Value > High { High } else { Value };
+#{Clamped: clamped}"#,
+    },
+    Template {
+        name: "Compare",
+        description: "Whether the first value is less, equal or greater",
+        inputs: &["A", "B"],
+        outputs: &["Less", "Equal", "Greater"],
+        code: "#{Less: A < B, Equal: A == B, Greater: A > B}",
+    },
+    Template {
+        name: "Concat",
+        description: "Joins two values as text",
+        inputs: &["A", "B"],
+        outputs: &["Text"],
+        code: "#{Text: `${A}${B}`}",
+    },
+];
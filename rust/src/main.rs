@@ -13,7 +13,7 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Rust scripter",
         native_options,
-        Box::new(|cc| Ok(Box::new(rust_labview::TemplateApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(rust_scripter::TemplateApp::new(cc)))),
     )
 }
 
@@ -26,6 +26,11 @@ fn main() {
     // Redirect `log` message to `console.log` and friends:
     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
 
+    // The executor worker loads this module too, but has no page to draw on.
+    if web_sys::window().is_none() {
+        return;
+    }
+
     let web_options = eframe::WebOptions::default();
 
     wasm_bindgen_futures::spawn_local(async {
@@ -52,5 +57,5 @@ fn main() {
 
 #[no_mangle]
 pub fn add(a: i32, b: i32) -> i32 {
-    return a + b;
+    a + b
 }
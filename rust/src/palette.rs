@@ -0,0 +1,277 @@
+use egui::{Align2, Area, Frame, Id, Key, Modifiers, Order, RichText, TextEdit};
+use serde::{Deserialize, Serialize};
+
+const MAX_RESULTS: usize = 12;
+const PALETTE_WIDTH: f32 = 360.0;
+
+/// Actions of the app that don't need a pointer.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Command {
+    AddFunction,
+    AddDelay,
+    AddComment,
+    AddNote,
+    RunAll,
+    StepN,
+    AutoLayout,
+    TidySelection,
+    ToggleGrid,
+    ToggleSnap,
+    ToggleLinkValues,
+    StartDebugging,
+    DebugStep,
+    DebugContinue,
+    StopDebugging,
+    ClearTrace,
+    Save,
+    ShowShortcuts,
+    CopyProject,
+    ExportScript,
+    ImportScript,
+    ExportDot,
+    ExportMermaid,
+    ExportSvg,
+    RunTests,
+}
+
+impl Command {
+    pub const ALL: [Command; 25] = [
+        Command::AddFunction,
+        Command::AddDelay,
+        Command::AddComment,
+        Command::AddNote,
+        Command::RunAll,
+        Command::StepN,
+        Command::AutoLayout,
+        Command::TidySelection,
+        Command::ToggleGrid,
+        Command::ToggleSnap,
+        Command::ToggleLinkValues,
+        Command::StartDebugging,
+        Command::DebugStep,
+        Command::DebugContinue,
+        Command::StopDebugging,
+        Command::ClearTrace,
+        Command::Save,
+        Command::ShowShortcuts,
+        Command::CopyProject,
+        Command::ExportScript,
+        Command::ImportScript,
+        Command::ExportDot,
+        Command::ExportMermaid,
+        Command::ExportSvg,
+        Command::RunTests,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::AddFunction => "Add function",
+            Command::AddDelay => "Add delay",
+            Command::AddComment => "Add comment",
+            Command::AddNote => "Add note",
+            Command::RunAll => "Run all",
+            Command::StepN => "Step N times",
+            Command::AutoLayout => "Auto layout",
+            Command::TidySelection => "Tidy selection",
+            Command::ToggleGrid => "Toggle grid",
+            Command::ToggleSnap => "Toggle snap to grid",
+            Command::ToggleLinkValues => "Toggle values on links",
+            Command::StartDebugging => "Start debugging",
+            Command::DebugStep => "Debug: step",
+            Command::DebugContinue => "Debug: continue",
+            Command::StopDebugging => "Stop debugging",
+            Command::ClearTrace => "Clear trace",
+            Command::Save => "Save",
+            Command::ShowShortcuts => "Keyboard shortcuts",
+            Command::CopyProject => "Copy project JSON",
+            Command::ExportScript => "Export as script",
+            Command::ImportScript => "Import script",
+            Command::ExportDot => "Export as DOT",
+            Command::ExportMermaid => "Export as Mermaid",
+            Command::ExportSvg => "Export as SVG",
+            Command::RunTests => "Run tests",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteItem {
+    /// Jump to the node with this id.
+    Node(u16),
+    /// Insert the library template with this index.
+    Template(usize),
+    Command(Command),
+}
+
+pub struct PaletteEntry {
+    pub item: PaletteItem,
+    pub label: String,
+    pub detail: &'static str,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteMode {
+    /// Nodes, templates and commands.
+    Everything,
+    CommandsOnly,
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    mode: Option<PaletteMode>,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn open(&mut self, mode: PaletteMode) {
+        self.mode = Some(mode);
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.mode = None;
+    }
+
+    pub fn mode(&self) -> Option<PaletteMode> {
+        self.mode
+    }
+
+    /// Shows the palette over `entries` sorted by how well they match the
+    /// query. Returns the entry picked with Enter or a click.
+    pub fn show(&mut self, ctx: &egui::Context, entries: Vec<PaletteEntry>) -> Option<PaletteItem> {
+        self.mode?;
+
+        let mut matches: Vec<(i32, PaletteEntry)> = entries
+            .into_iter()
+            .filter_map(|entry| Some((fuzzy_score(&self.query, &entry.label)?, entry)))
+            .collect();
+        matches.sort_by_key(|(score, _)| -score);
+        matches.truncate(MAX_RESULTS);
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down && self.selected + 1 < matches.len() {
+            self.selected += 1;
+        }
+
+        let mut clicked = None;
+        let area_response = Area::new(Id::new("command_palette"))
+            .order(Order::Foreground)
+            .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(PALETTE_WIDTH);
+                    let hint = match self.mode {
+                        Some(PaletteMode::CommandsOnly) => "Run a command…",
+                        _ => "Go to node, insert template or run a command…",
+                    };
+                    let edit_response = ui.add(
+                        TextEdit::singleline(&mut self.query)
+                            .hint_text(hint)
+                            .desired_width(f32::INFINITY),
+                    );
+                    edit_response.request_focus();
+                    if edit_response.changed() {
+                        self.selected = 0;
+                    }
+
+                    ui.separator();
+                    if matches.is_empty() {
+                        ui.weak("No matches");
+                    }
+                    for (index, (_, entry)) in matches.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let response =
+                                ui.selectable_label(index == self.selected, &entry.label);
+                            if response.clicked() {
+                                clicked = Some(index);
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.label(RichText::new(entry.detail).weak().small());
+                                },
+                            );
+                        });
+                    }
+                });
+            })
+            .response;
+
+        let clicked_outside = ctx.input(|i| i.pointer.any_pressed())
+            && ctx
+                .pointer_latest_pos()
+                .is_some_and(|pos| !area_response.rect.contains(pos));
+        if escape || clicked_outside {
+            self.close();
+            return None;
+        }
+
+        let picked = clicked.or(enter.then_some(self.selected))?;
+        let item = matches.get(picked).map(|(_, entry)| entry.item);
+        self.close();
+        item
+    }
+}
+
+/// Scores `text` against `query` when every query character appears in it in
+/// order. Consecutive characters and characters starting a word score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        let query_char = query_char.to_ascii_lowercase();
+        let found =
+            (position..text.len()).find(|&index| text[index].to_ascii_lowercase() == query_char)?;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        let is_word_start = found == 0 || !text[found - 1].is_alphanumeric();
+        if is_word_start {
+            score += 8;
+        }
+        if previous_match.is_none() {
+            score -= found as i32;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_characters_must_appear_in_order() {
+        assert!(fuzzy_score("ra", "Run all").is_some());
+        assert!(fuzzy_score("RUN", "run all").is_some());
+        assert!(fuzzy_score("run all", "Run all").is_some());
+        assert_eq!(fuzzy_score("da", "Add"), None);
+        assert_eq!(fuzzy_score("xyz", "Add"), None);
+    }
+
+    #[test]
+    fn word_starts_and_consecutive_characters_score_higher() {
+        assert!(fuzzy_score("ra", "Run all") > fuzzy_score("ra", "Bread"));
+        assert!(fuzzy_score("ad", "Add") > fuzzy_score("ad", "Abcd"));
+    }
+}
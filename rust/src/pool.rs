@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use rhai::{Dynamic, Engine};
+
+use crate::executor::WaveRunner;
+use crate::graph::Graph;
+use crate::trace::TraceEntry;
+
+type Job = Box<dyn FnOnce(&mut Engine) + Send>;
+
+/// Fixed set of threads, each with its own engine, running the functions of a
+/// graph that don't depend on each other.
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// One thread per core.
+    pub fn new() -> Self {
+        let size = std::thread::available_parallelism().map_or(2, |size| size.get());
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let threads = (0..size)
+            .map(|index| {
+                let receiver = receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("graph-runner-{index}"))
+                    .spawn(move || {
+                        let mut engine = Engine::new();
+                        loop {
+                            let job = receiver.lock().unwrap().recv();
+                            match job {
+                                Ok(job) => job(&mut engine),
+                                // The pool was dropped.
+                                Err(_) => break,
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn a graph runner thread")
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            threads,
+        }
+    }
+
+    pub fn spawn(&self, job: impl FnOnce(&mut Engine) + Send + 'static) {
+        if let Some(sender) = &self.sender {
+            sender
+                .send(Box::new(job))
+                .expect("Graph runner threads stopped");
+        }
+    }
+}
+
+impl Default for ThreadPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.sender = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Runs the waves of one graph on the pool.
+pub struct PoolRunner<'a> {
+    pub pool: &'a ThreadPool,
+    /// The cancel flag of the run. Functions still running when it is set
+    /// stop with an error instead of holding on to their thread.
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// Every function of the wave on its own thread.
+impl WaveRunner for PoolRunner<'_> {
+    fn run_wave(&mut self, graph: &mut Graph, wave: &[u16]) -> Vec<TraceEntry> {
+        let (sender, receiver) = mpsc::channel();
+        for (index, function_id) in wave.iter().copied().enumerate() {
+            let Some(mut runnable) = graph.functions.get(&function_id).cloned() else {
+                continue;
+            };
+            let sender = sender.clone();
+            let cancel = self.cancel.clone();
+            self.pool.spawn(move |engine| {
+                engine.on_progress(move |_| {
+                    cancel
+                        .load(Ordering::Relaxed)
+                        .then(|| Dynamic::from("Run cancelled"))
+                });
+                let entry = runnable.run_traced(engine, function_id);
+                let _ = sender.send((index, function_id, runnable, entry));
+            });
+        }
+        drop(sender);
+
+        let mut results: Vec<_> = receiver.iter().collect();
+        results.sort_by_key(|(index, ..)| *index);
+        results
+            .into_iter()
+            .map(|(_, function_id, runnable, entry)| {
+                graph.functions.insert(function_id, runnable);
+                graph.propagate_outputs(function_id);
+                entry
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runnable::Runnable;
+
+    #[test]
+    fn cancel_stops_a_function_stuck_in_a_loop() {
+        let mut graph = Graph::default();
+        let runnable =
+            Runnable::with_signature("Spin".to_owned(), "loop {}".to_owned(), &[], &["Out"]);
+        graph.functions.insert(1, runnable);
+
+        let pool = ThreadPool::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stopper = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                cancel.store(true, Ordering::Relaxed);
+            })
+        };
+        let mut runner = PoolRunner {
+            pool: &pool,
+            cancel,
+        };
+
+        let entries = runner.run_wave(&mut graph, &[1]);
+        stopper.join().unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].error.is_some());
+    }
+}
@@ -0,0 +1,357 @@
+use egui::epaint::CubicBezierShape;
+use egui::{Pos2, Rect, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How far a link goes straight out of a port before it turns.
+const STUB: f32 = 30.0;
+/// Distance kept between a detouring link and the nodes it goes around.
+const CLEARANCE: f32 = 20.0;
+const ORTHOGONAL_SEARCH_STEP: f32 = 10.0;
+const ORTHOGONAL_SEARCH_STEPS: usize = 20;
+const BEZIER_TOLERANCE: f32 = 0.5;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum LinkStyle {
+    #[default]
+    Bezier,
+    Straight,
+    Orthogonal,
+}
+
+impl LinkStyle {
+    pub const ALL: [LinkStyle; 3] = [
+        LinkStyle::Bezier,
+        LinkStyle::Straight,
+        LinkStyle::Orthogonal,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkStyle::Bezier => "Bezier",
+            LinkStyle::Straight => "Straight",
+            LinkStyle::Orthogonal => "Orthogonal",
+        }
+    }
+}
+
+/// Routes a link through `points` (start, reroute knots, end) and returns one
+/// polyline per pair of consecutive points. Links leave to the right and enter
+/// from the left and detour around `obstacles` standing in their way.
+pub fn route(style: LinkStyle, points: &[Pos2], obstacles: &[Rect]) -> Vec<Vec<Pos2>> {
+    points
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (pair[0], pair[1]);
+            match style {
+                LinkStyle::Bezier => bezier(from, to, obstacles),
+                LinkStyle::Straight => straight(from, to, obstacles),
+                LinkStyle::Orthogonal => orthogonal(from, to, obstacles),
+            }
+        })
+        .collect()
+}
+
+fn bezier(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<Pos2> {
+    let dx = to.x - from.x;
+    if dx > 0.0 {
+        let curve = |from: Pos2, to: Pos2| {
+            let handle = ((to.x - from.x) / 2.0).max(STUB);
+            flatten([
+                from,
+                from + Vec2::new(handle, 0.0),
+                to - Vec2::new(handle, 0.0),
+                to,
+            ])
+        };
+        let direct = curve(from, to);
+        let others = passed_obstacles(from, to, obstacles);
+        if !crosses_any(&direct, &others) {
+            return direct;
+        }
+
+        // Forwards through nodes: two curves meeting on a row above or below them.
+        let middle_x = (from.x + to.x) / 2.0;
+        let detour = |y: f32| {
+            let mut points = curve(from, Pos2::new(middle_x, y));
+            points.extend(curve(Pos2::new(middle_x, y), to).into_iter().skip(1));
+            points
+        };
+        return detour_rows(from, to, obstacles)
+            .into_iter()
+            .map(detour)
+            .find(|path| !crosses_any(path, &others))
+            .unwrap_or_else(|| detour(detour_y(from, to, obstacles)));
+    }
+
+    // Backwards: loop out of the start, run along the detour row, loop into the end.
+    let y = detour_y(from, to, obstacles);
+    let loop_width = STUB * 2.0;
+    let mut points = flatten([
+        from,
+        from + Vec2::new(loop_width, 0.0),
+        Pos2::new(from.x + loop_width, y),
+        Pos2::new(from.x, y),
+    ]);
+    points.extend(flatten([
+        Pos2::new(from.x, y),
+        Pos2::new((from.x + to.x) / 2.0, y),
+        Pos2::new((from.x + to.x) / 2.0, y),
+        Pos2::new(to.x, y),
+    ]));
+    points.extend(flatten([
+        Pos2::new(to.x, y),
+        Pos2::new(to.x - loop_width, y),
+        to - Vec2::new(loop_width, 0.0),
+        to,
+    ]));
+    points
+}
+
+fn straight(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<Pos2> {
+    let direct = vec![from, to];
+    let others = passed_obstacles(from, to, obstacles);
+    if !crosses_any(&direct, &others) {
+        return direct;
+    }
+
+    let detour = |y: f32| {
+        vec![
+            from,
+            Pos2::new(from.x + STUB, y),
+            Pos2::new(to.x - STUB, y),
+            to,
+        ]
+    };
+    detour_rows(from, to, obstacles)
+        .into_iter()
+        .map(detour)
+        .find(|path| !crosses_any(path, &others))
+        .unwrap_or_else(|| detour(detour_y(from, to, obstacles)))
+}
+
+fn orthogonal(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<Pos2> {
+    let others = passed_obstacles(from, to, obstacles);
+    if to.x - from.x >= STUB * 2.0 {
+        let middle = (from.x + to.x) / 2.0;
+        let max_offset = ((to.x - from.x) / 2.0 - STUB).max(0.0);
+        let candidates = (0..ORTHOGONAL_SEARCH_STEPS).flat_map(|step| {
+            let offset = step as f32 * ORTHOGONAL_SEARCH_STEP;
+            [middle + offset, middle - offset]
+        });
+        let path = |x: f32| vec![from, Pos2::new(x, from.y), Pos2::new(x, to.y), to];
+        if let Some(path) = candidates
+            .filter(|x| (x - middle).abs() <= max_offset)
+            .map(path)
+            .find(|path| !crosses_any(path, &others))
+        {
+            return path;
+        }
+    }
+
+    // Backwards, or no vertical leg gets past the nodes: run along a row.
+    let detour = |y: f32| {
+        vec![
+            from,
+            Pos2::new(from.x + STUB, from.y),
+            Pos2::new(from.x + STUB, y),
+            Pos2::new(to.x - STUB, y),
+            Pos2::new(to.x - STUB, to.y),
+            to,
+        ]
+    };
+    detour_rows(from, to, obstacles)
+        .into_iter()
+        .map(detour)
+        .find(|path| !crosses_any(path, &others))
+        .unwrap_or_else(|| detour(detour_y(from, to, obstacles)))
+}
+
+/// Row a detouring link can run along: above or below every node standing
+/// between its ends, whichever is closer. Without such nodes it's halfway between.
+fn detour_y(from: Pos2, to: Pos2, obstacles: &[Rect]) -> f32 {
+    detour_rows(from, to, obstacles)
+        .first()
+        .copied()
+        .unwrap_or((from.y + to.y) / 2.0)
+}
+
+/// Rows above and below the nodes standing between the ends of a link,
+/// the one with less vertical travel first. Empty when nothing is in the way.
+fn detour_rows(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<f32> {
+    let band = Rect::from_two_pos(
+        Pos2::new(to.x - STUB, from.y.min(to.y)),
+        Pos2::new(from.x + STUB, from.y.max(to.y)),
+    )
+    .expand(1.0);
+    let blocking: Vec<&Rect> = obstacles
+        .iter()
+        .filter(|rect| rect.intersects(band))
+        .collect();
+    if blocking.is_empty() {
+        return Vec::new();
+    }
+
+    let above = blocking
+        .iter()
+        .map(|rect| rect.top())
+        .fold(f32::INFINITY, f32::min)
+        - CLEARANCE;
+    let below = blocking
+        .iter()
+        .map(|rect| rect.bottom())
+        .fold(f32::NEG_INFINITY, f32::max)
+        + CLEARANCE;
+    let travel = |y: f32| (from.y - y).abs() + (to.y - y).abs();
+    if travel(above) < travel(below) {
+        vec![above, below]
+    } else {
+        vec![below, above]
+    }
+}
+
+/// Obstacles a link has to go around: all but the nodes its ends sit on.
+fn passed_obstacles(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<Rect> {
+    obstacles
+        .iter()
+        .filter(|rect| {
+            let rect = rect.expand(1.0);
+            !rect.contains(from) && !rect.contains(to)
+        })
+        .copied()
+        .collect()
+}
+
+fn crosses_any(path: &[Pos2], obstacles: &[Rect]) -> bool {
+    path.windows(2).any(|segment| {
+        obstacles
+            .iter()
+            .any(|rect| segment_crosses(segment[0], segment[1], rect.shrink(1.0)))
+    })
+}
+
+/// Whether the segment from `a` to `b` passes through `rect`, by clipping it
+/// against the four sides (Liang-Barsky).
+fn segment_crosses(a: Pos2, b: Pos2, rect: Rect) -> bool {
+    let d = b - a;
+    let (mut enter, mut exit) = (0.0_f32, 1.0_f32);
+    for (p, q) in [
+        (-d.x, a.x - rect.left()),
+        (d.x, rect.right() - a.x),
+        (-d.y, a.y - rect.top()),
+        (d.y, rect.bottom() - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            exit = exit.min(q / p);
+        }
+    }
+    enter <= exit
+}
+
+fn flatten(points: [Pos2; 4]) -> Vec<Pos2> {
+    CubicBezierShape::from_points_stroke(points, false, Default::default(), Stroke::NONE)
+        .flatten(Some(BEZIER_TOLERANCE))
+}
+
+/// Distance from `pos` to the closest point of the polyline.
+pub fn distance_to(path: &[Pos2], pos: Pos2) -> f32 {
+    path.windows(2)
+        .map(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let ab = b - a;
+            let t = if ab.length_sq() > 0.0 {
+                ((pos - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            pos.distance(a + ab * t)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+pub fn length(path: &[Pos2]) -> f32 {
+    path.windows(2)
+        .map(|segment| segment[0].distance(segment[1]))
+        .sum()
+}
+
+/// Point at `fraction` (0 to 1) of the polyline length.
+pub fn point_at(path: &[Pos2], fraction: f32) -> Pos2 {
+    let mut remaining = length(path) * fraction.clamp(0.0, 1.0);
+    for segment in path.windows(2) {
+        let segment_length = segment[0].distance(segment[1]);
+        if remaining <= segment_length && segment_length > 0.0 {
+            return segment[0] + (segment[1] - segment[0]) * (remaining / segment_length);
+        }
+        remaining -= segment_length;
+    }
+    path.last().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(x: f32, y: f32) -> Rect {
+        Rect::from_min_size(Pos2::new(x, y), Vec2::new(100.0, 60.0))
+    }
+
+    #[test]
+    fn links_without_nodes_in_between_go_direct() {
+        let (from, to) = (Pos2::new(0.0, 0.0), Pos2::new(300.0, 0.0));
+        let segments = route(LinkStyle::Straight, &[from, to], &[node(100.0, 100.0)]);
+        assert_eq!(segments, [vec![from, to]]);
+    }
+
+    #[test]
+    fn forward_links_go_around_nodes_in_between() {
+        let (from, to) = (Pos2::new(0.0, 30.0), Pos2::new(400.0, 30.0));
+        let obstacles = [node(150.0, 0.0)];
+        for style in LinkStyle::ALL {
+            let segments = route(style, &[from, to], &obstacles);
+            assert!(
+                !crosses_any(&segments.concat(), &obstacles),
+                "{} link crosses the node",
+                style.name()
+            );
+        }
+    }
+
+    #[test]
+    fn backward_links_detour_past_the_nodes_they_connect() {
+        let source = node(300.0, 0.0);
+        let target = node(0.0, 0.0);
+        let (from, to) = (Pos2::new(400.0, 30.0), Pos2::new(0.0, 30.0));
+        let path = route(LinkStyle::Orthogonal, &[from, to], &[source, target]).concat();
+        assert!(path.iter().any(|pos| pos.y > 60.0 || pos.y < 0.0));
+    }
+
+    #[test]
+    fn ends_sitting_on_their_nodes_are_not_obstacles() {
+        let source = node(0.0, 0.0);
+        let from = Pos2::new(95.0, 30.0);
+        let to = Pos2::new(300.0, 30.0);
+        assert!(passed_obstacles(from, to, &[source]).is_empty());
+    }
+
+    #[test]
+    fn diagonal_segments_only_cross_what_they_touch() {
+        let rect = node(0.0, 0.0);
+        assert!(segment_crosses(
+            Pos2::new(-10.0, -10.0),
+            Pos2::new(50.0, 50.0),
+            rect
+        ));
+        // The bounding box overlaps the rect, the segment doesn't.
+        assert!(!segment_crosses(
+            Pos2::new(-20.0, 10.0),
+            Pos2::new(10.0, -20.0),
+            rect
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+
+use emath::Pos2;
+use indexmap::IndexMap;
+use rhai::{Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::trace::{self, TraceEntry};
+
+const MAX_VALUE_HISTORY: usize = 16;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LinkVertex {
+    pub function_id: u16,
+    pub param_id: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FunctionParam {
+    pub param_name: String,
+    pub type_name: String,
+    pub pos: Pos2,
+    pub should_be_deleted: bool,
+    pub is_renaming: bool,
+    pub is_editing: bool,
+    pub last_value: Option<rhai::Dynamic>,
+    #[serde(skip)]
+    pub history: VecDeque<rhai::Dynamic>,
+}
+
+impl Default for FunctionParam {
+    fn default() -> Self {
+        Self {
+            param_name: "New...".to_string(),
+            type_name: "String".to_string(),
+            pos: Pos2::default(),
+            should_be_deleted: false,
+            is_renaming: false,
+            is_editing: false,
+            last_value: None,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+impl FunctionParam {
+    /// Replaces the value, keeping the previous one in the bounded history.
+    pub fn set_last_value(&mut self, value: Dynamic) {
+        if let Some(previous) = self.last_value.replace(value) {
+            self.history.push_back(previous);
+            if self.history.len() > MAX_VALUE_HISTORY {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Like [`Self::set_last_value`], but leaves the value and its history
+    /// alone when it didn't change. Returns whether it changed.
+    pub fn update_last_value(&mut self, value: Dynamic) -> bool {
+        let changed = self
+            .last_value
+            .as_ref()
+            .map_or(true, |old_value| !is_same_value(old_value, &value));
+        if changed {
+            self.set_last_value(value);
+        }
+        changed
+    }
+
+    fn default_with_name(name: &str) -> Self {
+        Self {
+            param_name: name.to_string(),
+            type_name: "String".to_string(),
+            pos: Pos2::default(),
+            should_be_deleted: false,
+            is_renaming: false,
+            is_editing: false,
+            last_value: None,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug, Default)]
+pub enum RunnableKind {
+    #[default]
+    Function,
+    /// Outputs what its input was on the previous iteration, which lets links form feedback loops.
+    Delay,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Runnable {
+    pub name: String,
+    pub code: String,
+    pub inputs: IndexMap<u16, FunctionParam>,
+    pub outputs: IndexMap<u16, FunctionParam>,
+    #[serde(default)]
+    pub kind: RunnableKind,
+    /// Value latched by a delay at the end of the previous iteration.
+    #[serde(default)]
+    pub state: Option<Dynamic>,
+}
+
+impl Default for Runnable {
+    fn default() -> Self {
+        let inputs = IndexMap::from_iter([
+            (
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Input1"),
+            ),
+            (
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Input2"),
+            ),
+            (
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Input3"),
+            ),
+        ]);
+
+        let outputs = IndexMap::from_iter([
+            (
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Output1"),
+            ),
+            (
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Output2"),
+            ),
+        ]);
+        Self {
+            name: "Function #0".to_owned(),
+            code: r#"let val = #{Output1: Input1, Output2: Input2};
+val"#
+                .to_string(),
+            inputs,
+            outputs,
+            kind: RunnableKind::Function,
+            state: None,
+        }
+    }
+}
+
+impl Runnable {
+    pub fn delay(name: String) -> Self {
+        Self {
+            name,
+            code: String::new(),
+            inputs: IndexMap::from_iter([(
+                fastrand::u16(..),
+                FunctionParam::default_with_name("In"),
+            )]),
+            outputs: IndexMap::from_iter([(
+                fastrand::u16(..),
+                FunctionParam::default_with_name("Out"),
+            )]),
+            kind: RunnableKind::Delay,
+            state: None,
+        }
+    }
+
+    /// A function with the given parameter names and code.
+    pub fn with_signature(name: String, code: String, inputs: &[&str], outputs: &[&str]) -> Self {
+        let params = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (fastrand::u16(..), FunctionParam::default_with_name(name)))
+                .collect()
+        };
+        Self {
+            name,
+            code,
+            inputs: params(inputs),
+            outputs: params(outputs),
+            kind: RunnableKind::Function,
+            state: None,
+        }
+    }
+
+    pub fn is_delay(&self) -> bool {
+        self.kind == RunnableKind::Delay
+    }
+
+    /// Stores the current input of a delay so it is emitted on the next iteration.
+    pub fn latch_state(&mut self) {
+        if let Some((_, input)) = self.inputs.first() {
+            self.state = input.last_value.clone();
+        }
+    }
+
+    #[cfg(feature = "editor")]
+    pub fn get_param_by_vertex(&self, vertex: &LinkVertex) -> Pos2 {
+        if let Some(input) = self.inputs.get(&vertex.param_id) {
+            return input.pos;
+        }
+        if let Some(output) = self.outputs.get(&vertex.param_id) {
+            return output.pos;
+        }
+        panic!(
+            "No vertex found with {}, {}",
+            vertex.function_id, vertex.param_id
+        )
+    }
+
+    pub fn run(&mut self, engine: &Engine) -> Result<(), String> {
+        if self.kind == RunnableKind::Delay {
+            let state = self.state.clone().unwrap_or(Dynamic::from_int(0));
+            if let Some((_, output)) = self.outputs.first_mut() {
+                output.set_last_value(state);
+            }
+            return Ok(());
+        }
+
+        let mut scope = Scope::new();
+        for input in self.inputs.values() {
+            scope.push_dynamic(
+                input.param_name.as_str(),
+                input.last_value.clone().unwrap_or(Dynamic::from_int(3)),
+            );
+        }
+        let result = engine
+            .eval_with_scope::<Map>(&mut scope, &self.code)
+            .map_err(|err| err.to_string())?;
+        for ele in self.outputs.iter_mut() {
+            if let Some(val) = result.get(ele.1.param_name.as_str()) {
+                ele.1.set_last_value(val.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the code and describes the run for the trace.
+    pub fn run_traced(&mut self, engine: &Engine, function_id: u16) -> TraceEntry {
+        let inputs = snapshot_values(&self.inputs);
+        let started_at = trace::now();
+        let result = self.run(engine);
+        let duration = trace::now() - started_at;
+
+        TraceEntry {
+            run_id: 0,
+            function_id,
+            function_name: self.name.clone(),
+            started_at,
+            duration,
+            inputs,
+            outputs: snapshot_values(&self.outputs),
+            error: result.err(),
+        }
+    }
+}
+
+pub fn is_same_value(a: &Dynamic, b: &Dynamic) -> bool {
+    a.type_name() == b.type_name() && a.to_string() == b.to_string()
+}
+
+fn snapshot_values(params: &IndexMap<u16, FunctionParam>) -> IndexMap<String, Option<Dynamic>> {
+    params
+        .values()
+        .map(|param| (param.param_name.clone(), param.last_value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_binds_string_inputs_as_values() {
+        let mut runnable = Runnable::with_signature(
+            "Greet".to_owned(),
+            r#"#{ Out: "hi " + Name }"#.to_owned(),
+            &["Name"],
+            &["Out"],
+        );
+        runnable.inputs[0].set_last_value("world".into());
+
+        runnable.run(&Engine::new()).unwrap();
+
+        let out = runnable.outputs[0].last_value.clone().unwrap();
+        assert_eq!(out.into_string().unwrap(), "hi world");
+    }
+
+    #[test]
+    fn unchanged_values_stay_out_of_the_history() {
+        let mut param = FunctionParam::default();
+        assert!(param.update_last_value(Dynamic::from_int(1)));
+        assert!(!param.update_last_value(Dynamic::from_int(1)));
+        assert!(param.update_last_value(Dynamic::from_int(2)));
+
+        assert_eq!(param.history.len(), 1);
+        assert_eq!(param.history[0].as_int(), Ok(1));
+    }
+}
@@ -0,0 +1,747 @@
+//! The other way around: a script with `fn`s becomes a graph with one function
+//! per `fn`. Inputs are the parameters, outputs the keys of the map the body
+//! returns. Calls in the main section become links, following the variables
+//! their results are kept in, and the scripts "Export as script" writes come
+//! back with their delays. Everything is read from the AST rhai parses, and
+//! what can't be turned into a link is reported as a warning.
+
+use std::collections::{HashMap, HashSet};
+
+use rhai::{
+    ASTNode, Dynamic, Engine, Expr, FnCallExpr, Map, Module, OptimizationLevel, Position,
+    ScriptFuncDef, Stmt, StmtBlock, AST,
+};
+
+use crate::graph::{Graph, GraphLink};
+use crate::runnable::{FunctionParam, LinkVertex, Runnable};
+
+/// A graph read from a script, with what couldn't be carried over.
+#[derive(Debug)]
+pub struct Imported {
+    pub graph: Graph,
+    /// Arguments and outputs that didn't become links or values, one line each.
+    pub warnings: Vec<String>,
+}
+
+/// What a variable of the main section holds.
+#[derive(Clone)]
+enum Binding {
+    /// The output map of a function, or what a delay emits.
+    Outputs(u16),
+    /// One output of a function.
+    Output { function_id: u16, name: String },
+    /// A value known without running anything.
+    Value(Dynamic),
+    /// The state of the delay with this id, assigned at the end of the script.
+    DelayState(u16),
+}
+
+/// Where the value passed to an input comes from.
+enum Source {
+    Output { function_id: u16, name: String },
+    Value(Dynamic),
+}
+
+/// A `fn` of the script and the function made for its first call.
+struct Definition {
+    runnable: Runnable,
+    function_id: u16,
+    calls: usize,
+}
+
+/// Reads `source` as a graph. Fails when it isn't valid rhai or has no `fn`.
+pub fn import(source: &str) -> Result<Imported, String> {
+    let mut engine = Engine::new();
+    // Keep the AST as written, constants folded away would lose their links.
+    engine.set_optimization_level(OptimizationLevel::None);
+    let ast = engine.compile(source).map_err(|err| err.to_string())?;
+
+    let mut functions: Vec<&ScriptFuncDef> = ast.iter_fn_def().map(|def| def.as_ref()).collect();
+    if functions.is_empty() {
+        return Err("The script doesn't define any function".to_owned());
+    }
+    functions.sort_by_key(|def| line_and_column(def.body.position()));
+
+    let mut importer = Importer {
+        engine,
+        graph: Graph::default(),
+        warnings: Vec::new(),
+        definitions: HashMap::new(),
+        bindings: HashMap::new(),
+    };
+    for def in functions {
+        importer.define(source, def);
+    }
+    importer.main(&ast);
+
+    let Importer {
+        graph,
+        mut warnings,
+        ..
+    } = importer;
+    for runnable in graph.functions.values() {
+        if runnable.outputs.is_empty() && !runnable.is_delay() {
+            warnings.push(format!(
+                "{}: can't tell which outputs the body returns, add them on the node",
+                runnable.name
+            ));
+        }
+    }
+    Ok(Imported { graph, warnings })
+}
+
+struct Importer {
+    engine: Engine,
+    graph: Graph,
+    warnings: Vec<String>,
+    /// By name and number of parameters.
+    definitions: HashMap<(String, usize), Definition>,
+    bindings: HashMap<String, Binding>,
+}
+
+impl Importer {
+    fn define(&mut self, source: &str, def: &ScriptFuncDef) {
+        let params: Vec<&str> = def.params.iter().map(|param| param.as_str()).collect();
+        let outputs = returned_keys(&def.body);
+        let outputs: Vec<&str> = outputs.iter().map(String::as_str).collect();
+        let runnable = Runnable::with_signature(
+            def.name.to_string(),
+            body_text(source, &def.body),
+            &params,
+            &outputs,
+        );
+        let function_id = self.insert_function(runnable.clone());
+        self.definitions.insert(
+            (def.name.to_string(), def.params.len()),
+            Definition {
+                runnable,
+                function_id,
+                calls: 0,
+            },
+        );
+    }
+
+    fn insert_function(&mut self, runnable: Runnable) -> u16 {
+        let mut function_id = fastrand::u16(..);
+        while self.graph.functions.contains_key(&function_id) {
+            function_id = fastrand::u16(..);
+        }
+        self.graph.functions.insert(function_id, runnable);
+        function_id
+    }
+
+    fn warn(&mut self, position: Position, message: String) {
+        match position.line() {
+            Some(line) => self.warnings.push(format!("Line {line}: {message}")),
+            None => self.warnings.push(message),
+        }
+    }
+
+    fn main(&mut self, ast: &AST) {
+        let reassigned: HashSet<&str> = ast
+            .statements()
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Assignment(assignment) if !assignment.0.is_op_assignment() => {
+                    variable_name(&assignment.1.lhs)
+                }
+                _ => None,
+            })
+            .collect();
+
+        for stmt in ast.statements() {
+            match stmt {
+                Stmt::Var(var, ..) => {
+                    let (ident, expr, _) = var.as_ref();
+                    if let Some(function_id) = self.delay(expr, &reassigned) {
+                        self.bindings
+                            .insert(ident.name.to_string(), Binding::Outputs(function_id));
+                        continue;
+                    }
+                    self.bind(ident.name.as_str(), expr);
+                }
+                Stmt::Assignment(assignment) => {
+                    let (op, binary) = assignment.as_ref();
+                    let name = variable_name(&binary.lhs).filter(|_| !op.is_op_assignment());
+                    let Some(name) = name else {
+                        self.visit_calls(&binary.rhs);
+                        continue;
+                    };
+                    match self.bindings.get(name).cloned() {
+                        Some(Binding::DelayState(function_id)) => {
+                            let param_id = self.graph.functions[&function_id]
+                                .inputs
+                                .keys()
+                                .next()
+                                .copied();
+                            if let Some(param_id) = param_id {
+                                self.pass(&binary.rhs, function_id, param_id);
+                            }
+                        }
+                        _ => self.bind(name, &binary.rhs),
+                    }
+                }
+                Stmt::FnCall(call, position) => {
+                    if self.call(call, *position).is_none() {
+                        for arg in call.args.iter() {
+                            self.visit_calls(arg);
+                        }
+                    }
+                }
+                Stmt::Expr(expr) => {
+                    self.visit_calls(expr);
+                }
+                _ => {
+                    if self.calls_a_definition(stmt) {
+                        self.warn(
+                            stmt.position(),
+                            "calls inside blocks, conditions and loops aren't imported".to_owned(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keeps what `expr` evaluates to under `name`, for the calls after it.
+    fn bind(&mut self, name: &str, expr: &Expr) {
+        let binding = match expr {
+            Expr::FnCall(call, position) => self.call(call, *position).map(Binding::Outputs),
+            Expr::Variable(..) => {
+                variable_name(expr).and_then(|var| self.bindings.get(var).cloned())
+            }
+            _ => None,
+        };
+        let binding = binding
+            .or_else(|| {
+                self.output(expr)
+                    .map(|(function_id, name)| Binding::Output { function_id, name })
+            })
+            .or_else(|| self.value(expr).map(Binding::Value));
+        match binding {
+            Some(binding) => {
+                self.bindings.insert(name.to_owned(), binding);
+            }
+            None => {
+                self.bindings.remove(name);
+                if self.visit_calls(expr) {
+                    self.warn(
+                        expr.position(),
+                        format!("{name} mixes calls with other code, its value isn't linked"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// `let name_out = #{ Out: name_state }` of an exported delay, whose
+    /// state is assigned at the end of the script.
+    fn delay(&mut self, expr: &Expr, reassigned: &HashSet<&str>) -> Option<u16> {
+        let Expr::Map(map, ..) = expr else {
+            return None;
+        };
+        let [(output, state)] = map.0.as_slice() else {
+            return None;
+        };
+        let state = variable_name(state).filter(|state| reassigned.contains(state))?;
+        let Some(Binding::Value(value)) = self.bindings.get(state).cloned() else {
+            return None;
+        };
+        let mut delay = Runnable::delay(state.trim_end_matches("_state").to_owned());
+        delay.state = Some(value);
+        if let Some((_, param)) = delay.outputs.first_mut() {
+            param.param_name = output.name.to_string();
+        }
+        let function_id = self.insert_function(delay);
+        self.bindings
+            .insert(state.to_owned(), Binding::DelayState(function_id));
+        Some(function_id)
+    }
+
+    /// The function a call of a `fn` of the script runs, with its arguments
+    /// linked. The first call uses the function of the `fn`, every other one
+    /// gets a copy. `None` for calls of anything else.
+    fn call(&mut self, call: &FnCallExpr, position: Position) -> Option<u16> {
+        if call.is_qualified() {
+            return None;
+        }
+        let key = (call.name.to_string(), call.args.len());
+        let definition = self.definitions.get_mut(&key)?;
+        definition.calls += 1;
+        let function_id = match definition.calls {
+            1 => definition.function_id,
+            calls => {
+                let mut runnable = definition.runnable.clone();
+                runnable.name = format!("{} ({calls})", runnable.name);
+                self.insert_function(runnable)
+            }
+        };
+
+        let params: Vec<u16> = self.graph.functions[&function_id]
+            .inputs
+            .keys()
+            .copied()
+            .collect();
+        for (index, (param_id, arg)) in params.into_iter().zip(call.args.iter()).enumerate() {
+            match self.source(arg) {
+                Ok(source) => self.connect(source, function_id, param_id),
+                Err(reason) => self.warn(
+                    arg.position().or_else(position),
+                    format!("argument {} of {}: {reason}", index + 1, call.name),
+                ),
+            }
+        }
+        Some(function_id)
+    }
+
+    /// Links `expr` to the input, or warns why it can't.
+    fn pass(&mut self, expr: &Expr, function_id: u16, param_id: u16) {
+        match self.source(expr) {
+            Ok(source) => self.connect(source, function_id, param_id),
+            Err(reason) => {
+                let name = self.graph.functions[&function_id].name.clone();
+                self.warn(expr.position(), format!("input of {name}: {reason}"));
+            }
+        }
+    }
+
+    /// Where the value of an argument comes from, or why it can't be linked.
+    fn source(&mut self, expr: &Expr) -> Result<Source, String> {
+        if let Some((function_id, name)) = self.output(expr) {
+            return Ok(Source::Output { function_id, name });
+        }
+        if let Some(var) = variable_name(expr) {
+            return match self.bindings.get(var).cloned() {
+                Some(Binding::Output { function_id, name }) => {
+                    Ok(Source::Output { function_id, name })
+                }
+                Some(Binding::Value(value)) => Ok(Source::Value(value)),
+                Some(Binding::Outputs(_)) => Err(format!(
+                    "{var} is a whole output map, pass one of its outputs like {var}.Out"
+                )),
+                Some(Binding::DelayState(_)) | None => {
+                    Err(format!("{var} isn't a value or a function output"))
+                }
+            };
+        }
+        if let Expr::FnCall(call, position) = expr {
+            if self.call(call, *position).is_some() {
+                return Err(format!(
+                    "{}(..) is a whole output map, pass one of its outputs like {}(..).Out",
+                    call.name, call.name
+                ));
+            }
+        }
+        if let Some(value) = self.value(expr) {
+            return Ok(Source::Value(value));
+        }
+        self.visit_calls(expr);
+        Err("isn't a value or a function output".to_owned())
+    }
+
+    /// `map.Key` or `map["Key"]` of an output map: a variable holding one or
+    /// a call of a `fn`.
+    fn output(&mut self, expr: &Expr) -> Option<(u16, String)> {
+        let (map, name) = match expr {
+            Expr::Dot(binary, ..) => match &binary.rhs {
+                Expr::Property(property, ..) => (&binary.lhs, property.2.to_string()),
+                _ => return None,
+            },
+            Expr::Index(binary, ..) => match &binary.rhs {
+                Expr::StringConstant(key, ..) => (&binary.lhs, key.to_string()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let function_id = match map {
+            Expr::FnCall(call, position) => self.call(call, *position)?,
+            _ => match self.bindings.get(variable_name(map)?) {
+                Some(Binding::Outputs(function_id)) => *function_id,
+                _ => return None,
+            },
+        };
+        Some((function_id, name))
+    }
+
+    /// What `expr` evaluates to, when it only uses literals, values known up
+    /// front and functions that aren't defined in the script.
+    fn value(&self, expr: &Expr) -> Option<Dynamic> {
+        if let Some(value) = expr.get_literal_value() {
+            return Some(value);
+        }
+        match expr {
+            Expr::Variable(..) => match self.bindings.get(variable_name(expr)?) {
+                Some(Binding::Value(value)) => Some(value.clone()),
+                _ => None,
+            },
+            Expr::Array(items, ..) => items
+                .iter()
+                .map(|item| self.value(item))
+                .collect::<Option<Vec<Dynamic>>>()
+                .map(Dynamic::from_array),
+            Expr::Map(map, ..) => map
+                .0
+                .iter()
+                .map(|(key, value)| Some((key.name.as_str().into(), self.value(value)?)))
+                .collect::<Option<Map>>()
+                .map(Dynamic::from_map),
+            Expr::FnCall(call, position) => {
+                let key = (call.name.to_string(), call.args.len());
+                if call.is_qualified() || self.definitions.contains_key(&key) {
+                    return None;
+                }
+                let mut call = call.as_ref().clone();
+                for arg in call.args.iter_mut() {
+                    *arg = Expr::DynamicConstant(self.value(arg)?.into(), arg.position());
+                }
+                let stmt = Stmt::Expr(Expr::FnCall(call.into(), *position).into());
+                let ast = AST::new([stmt], Module::new());
+                self.engine.eval_ast::<Dynamic>(&ast).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Imports the calls of `fn`s inside `expr`. Whether there were any.
+    fn visit_calls(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::FnCall(call, position) | Expr::MethodCall(call, position) => {
+                if matches!(expr, Expr::FnCall(..)) && self.call(call, *position).is_some() {
+                    return true;
+                }
+                call.args
+                    .iter()
+                    .fold(false, |found, arg| self.visit_calls(arg) | found)
+            }
+            Expr::Dot(binary, ..)
+            | Expr::Index(binary, ..)
+            | Expr::And(binary, ..)
+            | Expr::Or(binary, ..)
+            | Expr::Coalesce(binary, ..) => {
+                self.visit_calls(&binary.lhs) | self.visit_calls(&binary.rhs)
+            }
+            Expr::Array(items, ..) | Expr::InterpolatedString(items, ..) => items
+                .iter()
+                .fold(false, |found, item| self.visit_calls(item) | found),
+            Expr::Map(map, ..) => map
+                .0
+                .iter()
+                .fold(false, |found, (_, value)| self.visit_calls(value) | found),
+            _ => false,
+        }
+    }
+
+    fn calls_a_definition(&self, stmt: &Stmt) -> bool {
+        let mut found = false;
+        stmt.walk(&mut Vec::new(), &mut |path: &[ASTNode<'_>]| {
+            if let Some(ASTNode::Expr(Expr::FnCall(call, ..))) = path.last() {
+                let key = (call.name.to_string(), call.args.len());
+                found |= self.definitions.contains_key(&key);
+            }
+            !found
+        });
+        found
+    }
+
+    /// Links the input to the output it reads, adding the output when the
+    /// body didn't return it literally, or keeps the value the input gets.
+    fn connect(&mut self, source: Source, function_id: u16, param_id: u16) {
+        match source {
+            Source::Output {
+                function_id: start_id,
+                name,
+            } => {
+                let outputs = &mut self.graph.functions[&start_id].outputs;
+                let start_param = match outputs.iter().find(|(_, param)| param.param_name == name) {
+                    Some((param_id, _)) => *param_id,
+                    None => {
+                        let param_id = fastrand::u16(..);
+                        outputs.insert(
+                            param_id,
+                            FunctionParam {
+                                param_name: name,
+                                ..Default::default()
+                            },
+                        );
+                        param_id
+                    }
+                };
+                self.graph.links.push(GraphLink {
+                    start: LinkVertex {
+                        function_id: start_id,
+                        param_id: start_param,
+                    },
+                    end: LinkVertex {
+                        function_id,
+                        param_id,
+                    },
+                });
+            }
+            Source::Value(value) => {
+                let inputs = &mut self.graph.functions[&function_id].inputs;
+                if let Some(param) = inputs.get_mut(&param_id) {
+                    param.last_value = Some(value);
+                }
+            }
+        }
+    }
+}
+
+fn variable_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Variable(var, ..) => Some(var.1.as_str()),
+        _ => None,
+    }
+}
+
+fn line_and_column(position: Position) -> (usize, usize) {
+    (
+        position.line().unwrap_or(0),
+        position.position().unwrap_or(0),
+    )
+}
+
+/// Keys of the maps a body evaluates to: its last expression, anything after
+/// `return`, and for a variable the map it was made from and the keys set
+/// on it afterwards.
+fn returned_keys(body: &StmtBlock) -> Vec<String> {
+    let mut returned = Vec::new();
+    tail_expressions(body.statements(), &mut returned);
+    visit_statements(body.statements(), &mut |stmt| {
+        if let Stmt::Return(Some(expr), ..) = stmt {
+            returned.push(expr.as_ref());
+        }
+    });
+
+    let mut keys: Vec<String> = Vec::new();
+    let mut add = |key: &str| {
+        if !keys.iter().any(|known| known == key) {
+            keys.push(key.to_owned());
+        }
+    };
+    for expr in returned {
+        if let Expr::Map(map, ..) = expr {
+            map.0.iter().for_each(|(key, _)| add(&key.name));
+        }
+        let Some(name) = variable_name(expr) else {
+            continue;
+        };
+        visit_statements(body.statements(), &mut |stmt| match stmt {
+            Stmt::Var(var, ..) => {
+                if let (ident, Expr::Map(map, ..), _) = var.as_ref() {
+                    if ident.name == name {
+                        map.0.iter().for_each(|(key, _)| add(&key.name));
+                    }
+                }
+            }
+            Stmt::Assignment(assignment) => {
+                let key = match &assignment.1.lhs {
+                    Expr::Dot(binary, ..) => match &binary.rhs {
+                        Expr::Property(property, ..) => Some((&binary.lhs, property.2.as_str())),
+                        _ => None,
+                    },
+                    Expr::Index(binary, ..) => match &binary.rhs {
+                        Expr::StringConstant(key, ..) => Some((&binary.lhs, key.as_str())),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some((map, key)) = key {
+                    if variable_name(map) == Some(name) {
+                        add(key);
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+    keys
+}
+
+/// Every statement of `statements`, and of the blocks, branches and loops
+/// inside them.
+fn visit_statements<'a>(statements: &'a [Stmt], on_stmt: &mut impl FnMut(&'a Stmt)) {
+    for stmt in statements {
+        on_stmt(stmt);
+        match stmt {
+            Stmt::Block(block) => visit_statements(block.statements(), on_stmt),
+            Stmt::If(flow, ..)
+            | Stmt::While(flow, ..)
+            | Stmt::Do(flow, ..)
+            | Stmt::TryCatch(flow, ..) => {
+                visit_statements(flow.body.statements(), on_stmt);
+                visit_statements(flow.branch.statements(), on_stmt);
+            }
+            Stmt::For(for_loop, ..) => visit_statements(for_loop.2.body.statements(), on_stmt),
+            _ => {}
+        }
+    }
+}
+
+/// The expressions a block evaluates to, following both sides of an `if`.
+fn tail_expressions<'a>(statements: &'a [Stmt], tails: &mut Vec<&'a Expr>) {
+    match statements.last() {
+        Some(Stmt::Expr(expr)) => match expr.as_ref() {
+            Expr::Stmt(block) => tail_expressions(block.statements(), tails),
+            expr => tails.push(expr),
+        },
+        Some(Stmt::Block(block)) => tail_expressions(block.statements(), tails),
+        Some(Stmt::If(flow, ..)) => {
+            tail_expressions(flow.body.statements(), tails);
+            tail_expressions(flow.branch.statements(), tails);
+        }
+        _ => {}
+    }
+}
+
+/// The code between the braces of a body, as written.
+fn body_text(source: &str, body: &StmtBlock) -> String {
+    let span = body.span();
+    let (Some(start), Some(end)) = (offset(source, span.start()), offset(source, span.end()))
+    else {
+        return String::new();
+    };
+    dedent(source.get(start + 1..end).unwrap_or_default())
+}
+
+/// Byte offset of a position in `source`.
+fn offset(source: &str, position: Position) -> Option<usize> {
+    let line = position.line()?;
+    let column = position.position()?;
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    let (at, _) = source[line_start..].char_indices().nth(column - 1)?;
+    Some(line_start + at)
+}
+
+/// Removes the indentation every line shares, and the blank lines around.
+fn dedent(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    lines.join("\n").trim_matches('\n').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_id(graph: &Graph, name: &str) -> u16 {
+        graph
+            .functions
+            .iter()
+            .find(|(_, runnable)| runnable.name == name)
+            .map(|(function_id, _)| *function_id)
+            .unwrap()
+    }
+
+    fn linked(graph: &Graph, from: &str, to: &str) -> bool {
+        let (from, to) = (function_id(graph, from), function_id(graph, to));
+        graph
+            .links
+            .iter()
+            .any(|link| link.start.function_id == from && link.end.function_id == to)
+    }
+
+    #[test]
+    fn calls_nested_in_arguments_become_links() {
+        let imported = import(
+            "fn f(x) { #{ o: x } }
+             fn g(y) { #{ r: y } }
+             g(f(2).o);",
+        )
+        .unwrap();
+
+        assert!(linked(&imported.graph, "f", "g"));
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    }
+
+    #[test]
+    fn variables_holding_an_output_become_links() {
+        let imported = import(
+            "fn f(x) { #{ o: x } }
+             fn g(y) { #{ r: y } }
+             let a = f(1);
+             let b = a.o;
+             g(b);",
+        )
+        .unwrap();
+
+        assert!(linked(&imported.graph, "f", "g"));
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    }
+
+    #[test]
+    fn every_call_of_a_function_gets_its_own_node() {
+        let imported = import(
+            r#"fn diff(a, b) { #{ diff: a - b } }
+               fn scale(x) { #{ out: x * 2 } }
+               let r = diff(5, 3);
+               let s = scale(r["diff"]);
+               let t = scale(r["diff"]);"#,
+        )
+        .unwrap();
+
+        let graph = &imported.graph;
+        assert_eq!(graph.functions.len(), 3);
+        assert!(linked(graph, "diff", "scale"));
+        assert!(linked(graph, "diff", "scale (2)"));
+        let diff = &graph.functions[&function_id(graph, "diff")];
+        assert_eq!(diff.inputs[0].last_value.clone().unwrap().as_int(), Ok(5));
+    }
+
+    #[test]
+    fn outputs_set_on_a_returned_variable_are_found() {
+        let imported = import(
+            "fn m(x) {
+                 let m = #{};
+                 m.out = x;
+                 m[\"twice\"] = x * 2;
+                 m
+             }",
+        )
+        .unwrap();
+
+        let runnable = imported.graph.functions.values().next().unwrap();
+        let outputs: Vec<&str> = runnable
+            .outputs
+            .values()
+            .map(|param| param.param_name.as_str())
+            .collect();
+        assert_eq!(outputs, ["out", "twice"]);
+        assert!(runnable.code.starts_with("let m = #{};"));
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    }
+
+    #[test]
+    fn what_cant_be_linked_is_reported() {
+        let imported = import(
+            "fn f(x) { helper(x) }
+             fn g(y) { #{ r: y } }
+             g(f(2));",
+        )
+        .unwrap();
+
+        assert!(imported.graph.links.is_empty());
+        assert_eq!(imported.warnings.len(), 2, "{:?}", imported.warnings);
+        assert!(imported.warnings[0].starts_with("Line 3: argument 1 of g"));
+        assert!(imported.warnings[1].starts_with("f: can't tell which outputs"));
+    }
+
+    #[test]
+    fn scripts_without_functions_are_rejected() {
+        assert!(import("let a = 1;").is_err());
+        assert!(import("fn f( {").is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! A graph as one standalone rhai script: every function becomes a `fn`
+//! returning its output map, and the main section calls them in execution
+//! order, passing outputs to the inputs they are linked to. The script
+//! evaluates to the graph outputs, keyed by `Function.Param`. [`import`]
+//! reads such scripts back.
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use rhai::Dynamic;
+
+use crate::graph::Graph;
+use crate::runnable::Runnable;
+
+mod import;
+
+pub use import::{import, Imported};
+
+/// What unlinked inputs without a value get, the same as when they run.
+const DEFAULT_INPUT: &str = "3";
+/// What a delay emits before anything was latched.
+const DEFAULT_DELAY_STATE: &str = "0";
+
+/// Writes `graph` as a script. `None` when its links form a cycle.
+pub fn export(graph: &Graph) -> Option<String> {
+    let order = graph.execution_order()?;
+    let names = function_names(graph);
+    let mut script = String::new();
+
+    for (function_id, runnable) in graph.functions.iter() {
+        if runnable.is_delay() {
+            continue;
+        }
+        let params: Vec<&str> = runnable
+            .inputs
+            .values()
+            .map(|param| param.param_name.as_str())
+            .collect();
+        script += &format!("// {}\n", runnable.name);
+        script += &format!("fn {}({}) {{\n", names[function_id], params.join(", "));
+        for line in runnable.code.lines() {
+            script += &format!("    {line}\n");
+        }
+        script += "}\n\n";
+    }
+
+    script += "// Inputs nothing is linked to\n";
+    for (function_id, runnable) in graph.functions.iter() {
+        if runnable.is_delay() {
+            continue;
+        }
+        for (param_id, param) in runnable.inputs.iter() {
+            if source(graph, &names, *function_id, *param_id).is_none() {
+                let value = param
+                    .last_value
+                    .as_ref()
+                    .map_or(DEFAULT_INPUT.to_owned(), literal);
+                let variable = input_variable(&names[function_id], &param.param_name);
+                script += &format!("let {variable} = {value};\n");
+            }
+        }
+    }
+
+    let delays: Vec<(u16, &Runnable)> = graph
+        .functions
+        .iter()
+        .filter(|(_, runnable)| runnable.is_delay())
+        .map(|(id, runnable)| (*id, runnable))
+        .collect();
+    if !delays.is_empty() {
+        script += "\n// Values the delays emit\n";
+        for (function_id, runnable) in delays.iter() {
+            let state = runnable
+                .state
+                .as_ref()
+                .map_or(DEFAULT_DELAY_STATE.to_owned(), literal);
+            script += &format!("let {}_state = {state};\n", names[function_id]);
+        }
+    }
+
+    script += "\n";
+    for function_id in order.iter() {
+        let runnable = &graph.functions[function_id];
+        let name = &names[function_id];
+        if runnable.is_delay() {
+            let output = runnable
+                .outputs
+                .values()
+                .next()
+                .map_or("Out", |param| param.param_name.as_str());
+            script += &format!("let {name}_out = #{{ {}: {name}_state }};\n", key(output));
+            continue;
+        }
+        let arguments: Vec<String> = runnable
+            .inputs
+            .iter()
+            .map(
+                |(param_id, param)| match source(graph, &names, *function_id, *param_id) {
+                    Some(output) => output,
+                    None => input_variable(name, &param.param_name),
+                },
+            )
+            .collect();
+        script += &format!("let {name}_out = {name}({});\n", arguments.join(", "));
+    }
+
+    for (function_id, runnable) in delays.iter() {
+        let input = runnable.inputs.keys().next().copied();
+        if let Some(source) =
+            input.and_then(|param_id| source(graph, &names, *function_id, param_id))
+        {
+            script += &format!("{}_state = {source};\n", names[function_id]);
+        }
+    }
+
+    let outputs: Vec<String> = graph
+        .outputs()
+        .into_iter()
+        .map(|port| {
+            format!(
+                "    {}: {}_out[{}]",
+                literal(&port.key.into()),
+                names[&port.function_id],
+                literal(&port.name.into())
+            )
+        })
+        .collect();
+    script += &format!("\n#{{\n{}\n}}\n", outputs.join(",\n"));
+    Some(script)
+}
+
+/// Expression of the output linked to an input, if any.
+fn source(
+    graph: &Graph,
+    names: &IndexMap<u16, String>,
+    function_id: u16,
+    param_id: u16,
+) -> Option<String> {
+    let link = graph
+        .links
+        .iter()
+        .find(|link| link.end.function_id == function_id && link.end.param_id == param_id)?;
+    let runnable = graph.functions.get(&link.start.function_id)?;
+    let output = runnable.outputs.get(&link.start.param_id)?;
+    Some(format!(
+        "{}_out[{}]",
+        names[&link.start.function_id],
+        literal(&output.param_name.clone().into())
+    ))
+}
+
+/// Unique identifiers for the functions, made from their names.
+fn function_names(graph: &Graph) -> IndexMap<u16, String> {
+    let mut taken = HashSet::new();
+    graph
+        .functions
+        .iter()
+        .map(|(function_id, runnable)| {
+            let mut name = identifier(&runnable.name);
+            if !taken.insert(name.clone()) {
+                name = format!("{name}_{function_id}");
+                taken.insert(name.clone());
+            }
+            (*function_id, name)
+        })
+        .collect()
+}
+
+fn input_variable(function: &str, param: &str) -> String {
+    format!("{function}_{}", identifier(param))
+}
+
+/// `Add #1` becomes `add_1`.
+pub fn identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c.to_ascii_lowercase());
+        } else if !identifier.is_empty() && !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_end_matches('_');
+    match identifier.chars().next() {
+        None => "function".to_owned(),
+        Some(first) if first.is_ascii_digit() => format!("f_{identifier}"),
+        Some(_) => identifier.to_owned(),
+    }
+}
+
+/// Map key, quoted unless it is a plain identifier.
+fn key(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_identifier {
+        name.to_owned()
+    } else {
+        literal(&name.to_owned().into())
+    }
+}
+
+/// A value as rhai source.
+fn literal(value: &Dynamic) -> String {
+    format!("{value:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::Engine;
+
+    use super::*;
+    use crate::graph::GraphLink;
+    use crate::runnable::LinkVertex;
+
+    fn link(graph: &mut Graph, start: (u16, usize), end: (u16, usize)) {
+        let vertex = |function_id: u16, param_id: u16| LinkVertex {
+            function_id,
+            param_id,
+        };
+        let start_param = graph.functions[&start.0].outputs.keys()[start.1];
+        let end_param = graph.functions[&end.0].inputs.keys()[end.1];
+        graph.links.push(GraphLink {
+            start: vertex(start.0, start_param),
+            end: vertex(end.0, end_param),
+        });
+    }
+
+    fn output(graph: &Graph, name: &str) -> Dynamic {
+        let runnable = graph
+            .functions
+            .values()
+            .find(|runnable| runnable.name == name)
+            .unwrap();
+        runnable.outputs[0].last_value.clone().unwrap()
+    }
+
+    #[test]
+    fn exported_scripts_import_back() {
+        let mut graph = Graph::default();
+        let mut greet = Runnable::with_signature(
+            "Greet".to_owned(),
+            r#"#{ Text: "hi " + Name }"#.to_owned(),
+            &["Name"],
+            &["Text"],
+        );
+        greet.inputs[0].set_last_value("world".into());
+        let shout = Runnable::with_signature(
+            "Shout".to_owned(),
+            "#{ Loud: Text.to_upper() }".to_owned(),
+            &["Text"],
+            &["Loud"],
+        );
+        let count = Runnable::with_signature(
+            "Count".to_owned(),
+            "#{ Next: Previous + 1 }".to_owned(),
+            &["Previous"],
+            &["Next"],
+        );
+        graph.functions.insert(1, greet);
+        graph.functions.insert(2, shout);
+        graph.functions.insert(3, count);
+        graph
+            .functions
+            .insert(4, Runnable::delay("Last count".to_owned()));
+        link(&mut graph, (1, 0), (2, 0));
+        link(&mut graph, (3, 0), (4, 0));
+        link(&mut graph, (4, 0), (3, 0));
+
+        let imported = import(&export(&graph).unwrap()).unwrap();
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        let mut graph = imported.graph;
+        assert_eq!(graph.functions.len(), 4);
+        assert_eq!(graph.links.len(), 3);
+
+        let order = graph.execution_order().unwrap();
+        for _ in 0..2 {
+            graph.step_with(&Engine::new(), &order, |entry| {
+                assert_eq!(entry.error, None, "{}", entry.function_name)
+            });
+        }
+        assert_eq!(output(&graph, "shout").into_string().unwrap(), "HI WORLD");
+        assert_eq!(output(&graph, "count").as_int(), Ok(2));
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use rhai::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::graph::Graph;
+
+#[cfg(feature = "editor")]
+mod view;
+
+#[cfg(feature = "editor")]
+pub use view::TestPanel;
+
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+/// Values to bind to the graph inputs and the outputs they must give, both
+/// keyed by `Function.Param`. Saved with the project.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub inputs: IndexMap<String, Value>,
+    /// Outputs left out aren't checked.
+    pub expected: IndexMap<String, Value>,
+    /// Largest difference allowed between an expected and an actual number.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    /// How many times the graph steps before the outputs are checked.
+    #[serde(default = "default_iterations")]
+    pub iterations: u32,
+}
+
+fn default_tolerance() -> f64 {
+    DEFAULT_TOLERANCE
+}
+
+fn default_iterations() -> u32 {
+    1
+}
+
+impl TestCase {
+    #[cfg(feature = "editor")]
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            inputs: IndexMap::new(),
+            expected: IndexMap::new(),
+            tolerance: DEFAULT_TOLERANCE,
+            iterations: 1,
+        }
+    }
+
+    /// Checks the outputs of `graph` once it ran the case, or reports why it
+    /// couldn't.
+    pub fn result(&self, graph: Result<Graph, String>) -> TestResult {
+        let (mismatches, error) = match graph {
+            Ok(graph) => (self.mismatches(&graph), None),
+            Err(error) => (Vec::new(), Some(error)),
+        };
+        TestResult {
+            name: self.name.clone(),
+            mismatches,
+            error,
+        }
+    }
+
+    fn mismatches(&self, graph: &Graph) -> Vec<Mismatch> {
+        let outputs = graph.outputs();
+        let mut mismatches = Vec::new();
+        for (key, expected) in self.expected.iter() {
+            let actual = outputs
+                .iter()
+                .find(|port| port.key == *key)
+                .map(|port| serde_json::to_value(&port.value).unwrap_or(Value::Null));
+            let is_match = actual
+                .as_ref()
+                .is_some_and(|actual| matches(expected, actual, self.tolerance));
+            if !is_match {
+                mismatches.push(Mismatch {
+                    key: key.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+/// An output that doesn't have its expected value.
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+    pub key: String,
+    pub expected: Value,
+    /// `None` when the graph has no output called `key`.
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.actual {
+            Some(ref actual) => match (self.expected.as_f64(), actual.as_f64()) {
+                (Some(expected), Some(number)) => write!(
+                    f,
+                    "{}: expected {}, got {actual} (off by {})",
+                    self.key,
+                    self.expected,
+                    (number - expected).abs()
+                ),
+                _ => write!(f, "{}: expected {}, got {actual}", self.key, self.expected),
+            },
+            None => write!(f, "{}: no graph output with this name", self.key),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub mismatches: Vec<Mismatch>,
+    /// Why the case couldn't run: an unknown input, a cycle or failing code.
+    pub error: Option<String>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.mismatches.is_empty()
+    }
+}
+
+/// Runs `case` on a copy of `graph` and compares the outputs it expects.
+pub fn run(graph: &Graph, engine: &Engine, case: &TestCase) -> TestResult {
+    let result = with_inputs(graph, case).and_then(|mut graph| {
+        let order = graph
+            .execution_order()
+            .ok_or("Links form a cycle, break it with a delay")?;
+        for _ in 0..case.iterations {
+            let mut errors = Vec::new();
+            graph.step_with(engine, &order, |entry| {
+                if let Some(message) = entry.error {
+                    errors.push(format!("{}: {message}", entry.function_name));
+                }
+            });
+            if !errors.is_empty() {
+                return Err(errors.join("\n"));
+            }
+        }
+        Ok(graph)
+    });
+    case.result(result)
+}
+
+/// A copy of `graph` with the inputs of `case` set, ready to run.
+pub fn with_inputs(graph: &Graph, case: &TestCase) -> Result<Graph, String> {
+    let mut graph = graph.clone();
+    for (key, value) in case.inputs.iter() {
+        let value = rhai::serde::to_dynamic(value).map_err(|err| err.to_string())?;
+        graph.set_input(key, value)?;
+    }
+    Ok(graph)
+}
+
+/// Equal values, with numbers (also inside arrays and maps) allowed to be
+/// `tolerance` apart.
+fn matches(expected: &Value, actual: &Value, tolerance: f64) -> bool {
+    match (expected, actual) {
+        (Value::Number(expected), Value::Number(actual)) => {
+            match (expected.as_f64(), actual.as_f64()) {
+                (Some(expected), Some(actual)) => (expected - actual).abs() <= tolerance,
+                _ => expected == actual,
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| matches(expected, actual, tolerance))
+        }
+        (Value::Object(expected), Value::Object(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().all(|(key, expected)| {
+                    actual
+                        .get(key)
+                        .is_some_and(|actual| matches(expected, actual, tolerance))
+                })
+        }
+        _ => expected == actual,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn numbers_match_within_the_tolerance() {
+        assert!(matches(&json!(1.0), &json!(1.0000001), 1e-6));
+        assert!(!matches(&json!(1.0), &json!(1.1), 1e-6));
+        assert!(matches(&json!(2), &json!(2), 0.0));
+        assert!(!matches(&json!(2), &json!("2"), 1.0));
+    }
+
+    #[test]
+    fn nested_arrays_and_maps_match_item_by_item() {
+        let expected = json!({ "points": [[0.0, 1.0], [2.0, 3.0]], "label": "a" });
+        let close = json!({ "points": [[0.0, 1.0000001], [2.0, 3.0]], "label": "a" });
+        let shorter = json!({ "points": [[0.0, 1.0]], "label": "a" });
+        let missing_key = json!({ "points": [[0.0, 1.0], [2.0, 3.0]] });
+        let other_label = json!({ "points": [[0.0, 1.0], [2.0, 3.0]], "label": "b" });
+
+        assert!(matches(&expected, &close, 1e-6));
+        assert!(!matches(&expected, &close, 0.0));
+        assert!(!matches(&expected, &shorter, 1e-6));
+        assert!(!matches(&expected, &missing_key, 1e-6));
+        assert!(!matches(&expected, &other_label, 1e-6));
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{HashMap, VecDeque};
+
+use egui::{CollapsingHeader, Color32, DragValue, RichText, TextEdit, Ui, Window};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{TestCase, TestResult};
+use crate::executor::{BackgroundExecutor, ExecutionMessage, RunScope};
+use crate::graph::{Graph, Port};
+use crate::trace::TraceEntry;
+
+const PASS_COLOR: Color32 = Color32::from_rgb(80, 200, 120);
+const FAIL_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
+
+/// JSON of the inputs and expected outputs of a case while it is edited.
+#[derive(Debug)]
+struct Draft {
+    inputs: String,
+    expected: String,
+    /// Why the text doesn't parse, the case keeps its last valid values.
+    error: Option<String>,
+}
+
+/// The test cases of the project and a window to edit and run them. Cases run
+/// one after another off the UI thread.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct TestPanel {
+    pub cases: Vec<TestCase>,
+    #[serde(skip)]
+    pub is_open: bool,
+    /// Result of the last run of each case, by index.
+    #[serde(skip)]
+    results: Vec<Option<TestResult>>,
+    #[serde(skip)]
+    drafts: HashMap<usize, Draft>,
+    #[serde(skip)]
+    executor: BackgroundExecutor,
+    /// Index of the case the executor runs.
+    #[serde(skip)]
+    running: Option<usize>,
+    /// Cases waiting for their turn, with their inputs already set.
+    #[serde(skip)]
+    queue: VecDeque<(usize, Graph)>,
+}
+
+impl TestPanel {
+    pub fn run_all(&mut self, graph: &Graph) {
+        self.stop();
+        self.results = vec![None; self.cases.len()];
+        for index in 0..self.cases.len() {
+            self.enqueue(graph, index);
+        }
+        self.start_next();
+    }
+
+    fn run_one(&mut self, graph: &Graph, index: usize) {
+        self.results.resize(self.cases.len(), None);
+        self.results[index] = None;
+        self.queue.retain(|(queued, _)| *queued != index);
+        if self.running == Some(index) {
+            self.executor.cancel();
+            self.running = None;
+        }
+        self.enqueue(graph, index);
+        if self.running.is_none() {
+            self.start_next();
+        }
+    }
+
+    /// Queues the case, or fails it right away when its inputs can't be set.
+    fn enqueue(&mut self, graph: &Graph, index: usize) {
+        let case = &self.cases[index];
+        match super::with_inputs(graph, case) {
+            Ok(graph) => self.queue.push_back((index, graph)),
+            Err(error) => self.results[index] = Some(case.result(Err(error))),
+        }
+    }
+
+    fn start_next(&mut self) {
+        if let Some((index, graph)) = self.queue.pop_front() {
+            let iterations = self.cases[index].iterations;
+            self.executor.start(graph, iterations, RunScope::Graph);
+            self.running = Some(index);
+        }
+    }
+
+    /// Cancels the running case and drops the queued ones.
+    fn stop(&mut self) {
+        self.executor.cancel();
+        self.running = None;
+        self.queue.clear();
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Checks the case that finished since the last frame and starts the next.
+    /// Called every frame, the window may be closed.
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        for (_, message) in self.executor.poll(ctx) {
+            let graph = match message {
+                ExecutionMessage::Finished { graph, trace, .. } => match errors(&trace) {
+                    Some(errors) => Err(errors),
+                    None => Ok(*graph),
+                },
+                ExecutionMessage::Failed { message, .. } => Err(message),
+                ExecutionMessage::Progress { .. } => continue,
+            };
+            if let Some(index) = self.running.take() {
+                self.results[index] = Some(self.cases[index].result(graph));
+            }
+        }
+        if !self.is_running() {
+            self.start_next();
+        }
+    }
+
+    /// A new case holding the values the graph inputs and outputs have now.
+    fn capture(&mut self, graph: &Graph) {
+        let values = |ports: Vec<Port>| -> IndexMap<String, Value> {
+            ports
+                .into_iter()
+                .filter_map(|port| {
+                    let value = serde_json::to_value(port.value?).ok()?;
+                    Some((port.key, value))
+                })
+                .collect()
+        };
+        let mut case = TestCase::new(format!("Case #{}", self.cases.len() + 1));
+        case.inputs = values(graph.inputs());
+        case.expected = values(graph.outputs());
+        self.cases.push(case);
+    }
+
+    fn remove(&mut self, index: usize) {
+        // Queued results are kept by index, which shifts.
+        self.stop();
+        self.cases.remove(index);
+        if index < self.results.len() {
+            self.results.remove(index);
+        }
+        self.drafts.clear();
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, graph: &Graph) {
+        let mut is_open = self.is_open;
+        Window::new("Tests")
+            .open(&mut is_open)
+            .vscroll(true)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!self.cases.is_empty(), egui::Button::new("▶ Run tests"))
+                        .clicked()
+                    {
+                        self.run_all(graph);
+                    }
+                    if self.is_running() && ui.button("⏹ Cancel").clicked() {
+                        self.stop();
+                    }
+                    if ui
+                        .button("➕ From current values")
+                        .on_hover_text(
+                            "Expects the outputs the graph has now for its current inputs",
+                        )
+                        .clicked()
+                    {
+                        self.capture(graph);
+                    }
+                });
+                let results: Vec<&TestResult> = self.results.iter().flatten().collect();
+                if !results.is_empty() {
+                    let passed = results.iter().filter(|result| result.passed()).count();
+                    let failed = results.len() - passed;
+                    let color = if failed == 0 { PASS_COLOR } else { FAIL_COLOR };
+                    ui.label(
+                        RichText::new(format!("{passed} passed, {failed} failed")).color(color),
+                    );
+                }
+                if self.cases.is_empty() {
+                    ui.label(
+                        RichText::new(
+                            "No test cases yet. Set the graph inputs, run it, check the outputs \
+                             and add a case from the current values.",
+                        )
+                        .weak(),
+                    );
+                }
+                ui.separator();
+
+                let mut run = None;
+                let mut remove = None;
+                for index in 0..self.cases.len() {
+                    ui.horizontal(|ui| {
+                        let result = self.results.get(index).and_then(Option::as_ref);
+                        let is_queued = self.queue.iter().any(|(queued, _)| *queued == index);
+                        match result {
+                            _ if self.running == Some(index) => {
+                                ui.spinner().on_hover_text("Running")
+                            }
+                            _ if is_queued => ui.label(RichText::new("…").weak()),
+                            Some(result) if result.passed() => {
+                                ui.label(RichText::new("✔").color(PASS_COLOR))
+                            }
+                            Some(_) => ui.label(RichText::new("✘").color(FAIL_COLOR)),
+                            None => ui.label(RichText::new("○").weak()),
+                        };
+                        ui.add(
+                            TextEdit::singleline(&mut self.cases[index].name).desired_width(180.0),
+                        );
+                        if ui
+                            .small_button("▶")
+                            .on_hover_text("Run this case")
+                            .clicked()
+                        {
+                            run = Some(index);
+                        }
+                        if ui.small_button("❌").on_hover_text("Delete").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    self.result_ui(ui, index);
+                    CollapsingHeader::new("Values")
+                        .id_salt(("test_case", index))
+                        .show(ui, |ui| self.case_ui(ui, index));
+                    ui.separator();
+                }
+                if let Some(index) = run {
+                    self.run_one(graph, index);
+                }
+                if let Some(index) = remove {
+                    self.remove(index);
+                }
+            });
+        self.is_open = is_open;
+    }
+
+    /// Why the last run of the case failed, one line per wrong output.
+    fn result_ui(&self, ui: &mut Ui, index: usize) {
+        let Some(Some(result)) = self.results.get(index) else {
+            return;
+        };
+        if let Some(ref error) = result.error {
+            ui.label(RichText::new(error).color(FAIL_COLOR));
+        }
+        for mismatch in result.mismatches.iter() {
+            ui.label(
+                RichText::new(mismatch.to_string())
+                    .monospace()
+                    .color(FAIL_COLOR),
+            );
+        }
+    }
+
+    fn case_ui(&mut self, ui: &mut Ui, index: usize) {
+        let case = &mut self.cases[index];
+        let draft = self.drafts.entry(index).or_insert_with(|| Draft {
+            inputs: serde_json::to_string_pretty(&case.inputs).unwrap_or_default(),
+            expected: serde_json::to_string_pretty(&case.expected).unwrap_or_default(),
+            error: None,
+        });
+
+        ui.label("Inputs");
+        let inputs_changed = ui
+            .add(
+                TextEdit::multiline(&mut draft.inputs)
+                    .code_editor()
+                    .desired_rows(3)
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+        ui.label("Expected outputs");
+        let expected_changed = ui
+            .add(
+                TextEdit::multiline(&mut draft.expected)
+                    .code_editor()
+                    .desired_rows(3)
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+        if inputs_changed || expected_changed {
+            let parse = |text: &str| serde_json::from_str::<IndexMap<String, Value>>(text);
+            match (parse(&draft.inputs), parse(&draft.expected)) {
+                (Ok(inputs), Ok(expected)) => {
+                    case.inputs = inputs;
+                    case.expected = expected;
+                    draft.error = None;
+                }
+                (Err(err), _) => draft.error = Some(format!("Inputs: {err}")),
+                (_, Err(err)) => draft.error = Some(format!("Expected outputs: {err}")),
+            }
+        }
+        if let Some(ref error) = draft.error {
+            ui.label(RichText::new(format!("⚠ {error}")).color(Color32::YELLOW));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Tolerance");
+            ui.add(
+                DragValue::new(&mut case.tolerance)
+                    .speed(1e-4)
+                    .range(0.0..=1e9)
+                    .max_decimals(9),
+            )
+            .on_hover_text("Largest difference allowed between expected and actual numbers");
+            ui.label("Iterations");
+            ui.add(DragValue::new(&mut case.iterations).range(1..=10_000));
+        });
+    }
+}
+
+/// The errors a run ran into, one line per function.
+fn errors(trace: &[TraceEntry]) -> Option<String> {
+    let mut errors: Vec<String> = Vec::new();
+    for entry in trace {
+        if let Some(ref message) = entry.error {
+            let error = format!("{}: {message}", entry.function_name);
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+    }
+    (!errors.is_empty()).then(|| errors.join("\n"))
+}
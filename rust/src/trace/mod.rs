@@ -0,0 +1,39 @@
+use std::sync::OnceLock;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+#[cfg(feature = "editor")]
+mod view;
+
+#[cfg(feature = "editor")]
+pub use view::ExecutionTrace;
+
+#[cfg(feature = "editor")]
+pub(crate) const MAX_TRACE_ENTRIES: usize = 2000;
+
+static TRACE_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Seconds elapsed since the first call, used to order trace entries.
+pub fn now() -> f64 {
+    TRACE_EPOCH
+        .get_or_init(Instant::now)
+        .elapsed()
+        .as_secs_f64()
+}
+
+/// A single `Runnable::run` invocation.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TraceEntry {
+    pub run_id: u64,
+    pub function_id: u16,
+    pub function_name: String,
+    /// Seconds since the trace epoch.
+    pub started_at: f64,
+    /// Seconds.
+    pub duration: f64,
+    pub inputs: IndexMap<String, Option<rhai::Dynamic>>,
+    pub outputs: IndexMap<String, Option<rhai::Dynamic>>,
+    pub error: Option<String>,
+}
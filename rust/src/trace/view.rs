@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use egui::{Align2, Color32, Rect, RichText, ScrollArea, Sense, TextStyle, Vec2};
+use indexmap::IndexMap;
+
+use super::{TraceEntry, MAX_TRACE_ENTRIES};
+
+#[derive(PartialEq, Debug, Default)]
+enum TraceView {
+    #[default]
+    Log,
+    Timeline,
+}
+
+/// Bounded log of executed functions. Entries collected during one frame form a run.
+#[derive(Default, Debug)]
+pub struct ExecutionTrace {
+    entries: VecDeque<TraceEntry>,
+    last_run_id: u64,
+    filter: String,
+    errors_only: bool,
+    view: TraceView,
+}
+
+impl ExecutionTrace {
+    /// Appends the entries of one execution wave as a new run.
+    pub fn record_run(&mut self, mut entries: Vec<TraceEntry>) {
+        if entries.is_empty() {
+            return;
+        }
+        self.last_run_id += 1;
+        entries.sort_by(|a, b| a.started_at.total_cmp(&b.started_at));
+        for mut entry in entries {
+            entry.run_id = self.last_run_id;
+            self.entries.push_back(entry);
+        }
+        while self.entries.len() > MAX_TRACE_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn last_run(&self) -> impl Iterator<Item = &TraceEntry> {
+        let last_run_id = self.last_run_id;
+        self.entries
+            .iter()
+            .filter(move |entry| entry.run_id == last_run_id)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap_or_default()
+    }
+
+    fn is_visible(&self, entry: &TraceEntry) -> bool {
+        (!self.errors_only || entry.error.is_some())
+            && entry
+                .function_name
+                .to_lowercase()
+                .contains(&self.filter.to_lowercase())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.view, TraceView::Log, "Log");
+            ui.selectable_value(&mut self.view, TraceView::Timeline, "Timeline");
+            ui.separator();
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut self.filter);
+            ui.checkbox(&mut self.errors_only, "Errors only");
+            ui.separator();
+            if ui.button("📋 Export JSON").clicked() {
+                let json = self.to_json();
+                ui.output_mut(|o| o.copied_text = json);
+            }
+            if ui.button("🗑 Clear").clicked() {
+                self.clear();
+            }
+        });
+        ui.separator();
+
+        match self.view {
+            TraceView::Log => self.log_ui(ui),
+            TraceView::Timeline => self.timeline_ui(ui),
+        }
+    }
+
+    fn log_ui(&self, ui: &mut egui::Ui) {
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in self.entries.iter().filter(|entry| self.is_visible(entry)) {
+                    let text = format!(
+                        "#{} {:>8.3}s  {} ({:.3} ms)  in: {}  out: {}",
+                        entry.run_id,
+                        entry.started_at,
+                        entry.function_name,
+                        entry.duration * 1000.0,
+                        format_values(&entry.inputs),
+                        format_values(&entry.outputs),
+                    );
+                    match entry.error {
+                        Some(ref error) => ui.label(
+                            RichText::new(format!("{text}  error: {error}")).color(Color32::RED),
+                        ),
+                        None => ui.label(text),
+                    };
+                }
+            });
+    }
+
+    fn timeline_ui(&self, ui: &mut egui::Ui) {
+        let entries: Vec<&TraceEntry> = self
+            .last_run()
+            .filter(|entry| self.is_visible(entry))
+            .collect();
+        let Some(first) = entries.first() else {
+            ui.label("Nothing was run yet");
+            return;
+        };
+
+        let run_start = first.started_at;
+        let run_end = entries
+            .iter()
+            .map(|entry| entry.started_at + entry.duration)
+            .fold(run_start, f64::max);
+        let run_duration = (run_end - run_start).max(f64::EPSILON);
+
+        ui.label(format!(
+            "Run #{}: {:.3} ms",
+            self.last_run_id,
+            run_duration * 1000.0
+        ));
+
+        let row_height = ui.text_style_height(&TextStyle::Small) + 4.0;
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let width = ui.available_width();
+                let (rect, _) = ui.allocate_exact_size(
+                    Vec2::new(width, row_height * entries.len() as f32),
+                    Sense::hover(),
+                );
+                let painter = ui.painter_at(rect);
+                let font_id = TextStyle::Small.resolve(ui.style());
+
+                for (row, entry) in entries.iter().enumerate() {
+                    let start = ((entry.started_at - run_start) / run_duration) as f32;
+                    let length = (entry.duration / run_duration) as f32;
+                    let bar = Rect::from_min_size(
+                        rect.min + Vec2::new(start * width, row as f32 * row_height),
+                        Vec2::new((length * width).max(2.0), row_height - 2.0),
+                    );
+                    let color = if entry.error.is_some() {
+                        Color32::from_rgb(200, 60, 60)
+                    } else {
+                        Color32::from_rgb(70, 130, 180)
+                    };
+                    painter.rect_filled(bar, 2.0, color);
+                    painter.text(
+                        bar.left_center() + Vec2::new(3.0, 0.0),
+                        Align2::LEFT_CENTER,
+                        format!("{} {:.3} ms", entry.function_name, entry.duration * 1000.0),
+                        font_id.clone(),
+                        ui.visuals().strong_text_color(),
+                    );
+                }
+            });
+    }
+}
+
+fn format_values(values: &IndexMap<String, Option<rhai::Dynamic>>) -> String {
+    values
+        .iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!("{name}={value}"),
+            None => format!("{name}=<none>"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,196 @@
+//! JavaScript API to use a project as a computation engine from the host page:
+//!
+//! ```js
+//! const graph = new ScripterGraph(projectJson);
+//! graph.setInputs({ "Add #1.A": 2, "Add #1.B": 3 });
+//! const outputs = graph.run(); // { "Add #1.Sum": 5 }
+//! ```
+//!
+//! and to follow what happens in the editor:
+//!
+//! ```js
+//! const id = subscribe("nodeError", (event) => showToast(event.message));
+//! unsubscribe(id);
+//! ```
+
+#[cfg(feature = "editor")]
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "editor")]
+use std::sync::atomic::AtomicBool;
+
+#[cfg(feature = "editor")]
+use rhai::Engine;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::embed::Project;
+#[cfg(feature = "editor")]
+use crate::events::GraphEvent;
+#[cfg(feature = "editor")]
+use crate::executor;
+
+/// Listens to every event type.
+#[cfg(feature = "editor")]
+const ANY_EVENT: &str = "*";
+
+#[cfg(feature = "editor")]
+struct Listener {
+    id: u32,
+    event: String,
+    callback: js_sys::Function,
+}
+
+#[cfg(feature = "editor")]
+thread_local! {
+    static LISTENERS: RefCell<Vec<Listener>> = const { RefCell::new(Vec::new()) };
+    static NEXT_LISTENER_ID: Cell<u32> = const { Cell::new(1) };
+}
+
+/// Calls `callback(event)` for every editor event of the given type, or of any
+/// type with `"*"`. Events are objects with a `type` field, e.g.
+/// `{ type: "nodeAdded", id: 123, name: "Function #3" }`. Returns an id for
+/// [`unsubscribe`].
+#[cfg(feature = "editor")]
+#[wasm_bindgen]
+pub fn subscribe(event: &str, callback: js_sys::Function) -> u32 {
+    let id = NEXT_LISTENER_ID.with(|next| next.replace(next.get() + 1));
+    LISTENERS.with_borrow_mut(|listeners| {
+        listeners.push(Listener {
+            id,
+            event: event.to_owned(),
+            callback,
+        })
+    });
+    id
+}
+
+/// Removes a listener added with [`subscribe`]. Returns whether it existed.
+#[cfg(feature = "editor")]
+#[wasm_bindgen]
+pub fn unsubscribe(id: u32) -> bool {
+    LISTENERS.with_borrow_mut(|listeners| {
+        let count = listeners.len();
+        listeners.retain(|listener| listener.id != id);
+        listeners.len() != count
+    })
+}
+
+#[cfg(feature = "editor")]
+pub fn emit(events: &[GraphEvent]) {
+    // Callbacks are collected first so they can subscribe or unsubscribe.
+    let calls: Vec<(js_sys::Function, &GraphEvent)> = LISTENERS.with_borrow(|listeners| {
+        events
+            .iter()
+            .flat_map(|event| {
+                listeners
+                    .iter()
+                    .filter(|listener| {
+                        listener.event == ANY_EVENT || listener.event == event.name()
+                    })
+                    .map(move |listener| (listener.callback.clone(), event))
+            })
+            .collect()
+    });
+    for (callback, event) in calls {
+        let result = to_js(event)
+            .map_err(JsValue::from)
+            .and_then(|payload| callback.call1(&JsValue::NULL, &payload));
+        if let Err(err) = result {
+            log::error!("Event listener for {} failed: {err:?}", event.name());
+        }
+    }
+}
+
+/// Entry point of the executor worker: runs a serialized `ExecutionRequest`
+/// and calls `post` with every serialized `ExecutionMessage`.
+#[cfg(feature = "editor")]
+#[wasm_bindgen(js_name = executeInWorker)]
+pub fn execute_in_worker(request_json: &str, post: js_sys::Function) {
+    let cancel = AtomicBool::new(false);
+    executor::execute_json(request_json, &mut Engine::new(), &cancel, |message| {
+        if let Err(err) = post.call1(&JsValue::NULL, &JsValue::from_str(&message)) {
+            log::error!("Can't report the run to the page: {err:?}");
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub struct ScripterGraph {
+    project: Project,
+}
+
+#[wasm_bindgen]
+impl ScripterGraph {
+    /// Loads a project exported with "Copy project JSON".
+    #[wasm_bindgen(constructor)]
+    pub fn new(project_json: &str) -> Result<ScripterGraph, JsError> {
+        Ok(Self {
+            project: Project::from_json(project_json)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = loadProject)]
+    pub fn load_project(&mut self, project_json: &str) -> Result<(), JsError> {
+        self.project = Project::from_json(project_json)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = projectJson)]
+    pub fn project_json(&self) -> String {
+        self.project.to_json()
+    }
+
+    /// Inputs nothing is linked to: `[{ key, function, name, value }]`.
+    pub fn inputs(&self) -> Result<JsValue, JsError> {
+        to_js(&self.project.inputs())
+    }
+
+    /// Outputs that aren't linked anywhere: `[{ key, function, name, value }]`.
+    pub fn outputs(&self) -> Result<JsValue, JsError> {
+        to_js(&self.project.outputs())
+    }
+
+    /// Sets one input by its `Function.Param` key.
+    #[wasm_bindgen(js_name = setInput)]
+    pub fn set_input(&mut self, key: &str, value: JsValue) -> Result<(), JsError> {
+        Ok(self.project.set_input_json(key, to_json_value(&value)?)?)
+    }
+
+    /// Sets several inputs from an object keyed by `Function.Param`.
+    #[wasm_bindgen(js_name = setInputs)]
+    pub fn set_inputs(&mut self, values: JsValue) -> Result<(), JsError> {
+        let serde_json::Value::Object(values) = to_json_value(&values)? else {
+            return Err(JsError::new("Inputs must be an object"));
+        };
+        Ok(self.project.set_inputs_json(values)?)
+    }
+
+    /// Runs the whole graph `iterations` times (once by default) and returns
+    /// the outputs as `{ "Function.Param": value }`.
+    pub fn run(&mut self, iterations: Option<u32>) -> Result<JsValue, JsError> {
+        self.project.run_iterations(iterations.unwrap_or(1))?;
+        self.output_values()
+    }
+
+    /// Current outputs as `{ "Function.Param": value }`, `null` before the first run.
+    #[wasm_bindgen(js_name = outputValues)]
+    pub fn output_values(&self) -> Result<JsValue, JsError> {
+        to_js(&self.project.output_values())
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let json = serde_json::to_string(value)?;
+    js_sys::JSON::parse(&json).map_err(|_| JsError::new("Value can't be passed to JS"))
+}
+
+fn to_json_value(value: &JsValue) -> Result<serde_json::Value, JsError> {
+    if value.is_undefined() {
+        return Ok(serde_json::Value::Null);
+    }
+    let json = js_sys::JSON::stringify(value)
+        .ok()
+        .and_then(|json| json.as_string())
+        .ok_or_else(|| JsError::new("Value can't be passed to Rust"))?;
+    Ok(serde_json::from_str(&json)?)
+}